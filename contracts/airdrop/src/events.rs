@@ -0,0 +1,49 @@
+use soroban_sdk::{symbol_short, Address, Env, IntoVal, Symbol, Val};
+
+use crate::Voucher;
+
+/// Published as `("airdrop", to, campaign)` with `(index, amount)`
+pub(crate) fn airdrop_claimed(e: &Env, to: Address, campaign: u32, index: u32, amount: i128) {
+    e.events()
+        .publish((symbol_short!("airdrop"), to, campaign), (index, amount));
+}
+
+/// Published as `("camp_fund", from, campaign)` with the amount added
+pub(crate) fn campaign_funded(e: &Env, from: Address, campaign: u32, amount: i128) {
+    e.events()
+        .publish((symbol_short!("camp_fund"), from, campaign), amount);
+}
+
+/// Published as `("camp_end", to, campaign)` with the unspent balance sent
+pub(crate) fn campaign_closed(e: &Env, to: Address, campaign: u32, amount: i128) {
+    e.events()
+        .publish((symbol_short!("camp_end"), to, campaign), amount);
+}
+
+/// Published as `("vch_fund", from, campaign)` with the amount added
+pub(crate) fn voucher_campaign_funded(e: &Env, from: Address, campaign: u32, amount: i128) {
+    e.events()
+        .publish((symbol_short!("vch_fund"), from, campaign), amount);
+}
+
+/// Published as `("vch_end", to, campaign)` with the unspent balance sent
+pub(crate) fn voucher_campaign_closed(e: &Env, to: Address, campaign: u32, amount: i128) {
+    e.events()
+        .publish((symbol_short!("vch_end"), to, campaign), amount);
+}
+
+/// Published as `("voucher", to)` with the redeemed voucher
+pub(crate) fn voucher_redeemed(e: &Env, to: Address, voucher: Voucher) {
+    e.events().publish((symbol_short!("voucher"), to), voucher);
+}
+
+/// Published by the setters as `("config_changed", field, caller)`
+/// with `(old, new)` as data
+pub(crate) fn config_changed<T>(e: &Env, field: Symbol, caller: Address, old: T, new: T)
+where
+    T: IntoVal<Env, Val>,
+{
+    let data: (Val, Val) = (old.into_val(e), new.into_val(e));
+    e.events()
+        .publish((Symbol::new(e, "config_changed"), field, caller), data);
+}
@@ -0,0 +1,300 @@
+//! Merkle airdrops: the admin publishes the root of an off-chain attendee
+//! list and each attendee claims their own leaf with a proof. Signed vouchers,
+//! in `voucher`, pay out the same way from campaigns of their own.
+//!
+//! A leaf is `sha256(index as u32 BE || to.to_xdr() || amount as i128 BE)`
+//! and an inner node hashes its two children in ascending byte order, so
+//! proofs carry no left/right flags.
+//!
+//! Each campaign pays from its own balance, topped up with `fund_campaign`.
+//! The airdrop lives apart from the faucet so that campaign funds never mix
+//! with the balance the faucet pays its claims from.
+
+#![no_std]
+
+mod events;
+mod ttl;
+mod voucher;
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contractmeta, contracttype, xdr::ToXdr, Address, Bytes,
+    BytesN, Env, Vec,
+};
+
+pub use voucher::{Voucher, VoucherCampaign};
+
+/// Claims tracked per bitmap word
+const WORD_BITS: u32 = 128;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,
+    CampaignCount,
+    Campaign(u32),
+    Claimed(u32, u32), // (campaign, bitmap word)
+    VoucherSigner,
+    VoucherNonce(u64),
+    VoucherCampaignCount,
+    VoucherCampaign(u32),
+}
+
+/// Errors returned by the airdrop. The numeric codes are part of the contract
+/// interface and must stay stable.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum AirdropError {
+    /// Amounts must be strictly positive
+    InvalidAmount = 1,
+    /// The caller is not the airdrop admin
+    Unauthorized = 2,
+    /// No airdrop or voucher campaign has this id
+    CampaignNotFound = 3,
+    /// The Merkle proof does not lead to the campaign root
+    InvalidProof = 4,
+    /// The leaf was already claimed
+    AlreadyClaimed = 5,
+    /// The campaign's funded balance is below the claimed amount
+    CampaignUnderfunded = 6,
+    /// An amount does not fit in i128
+    Overflow = 7,
+    /// No ed25519 key is registered to sign vouchers
+    NoVoucherSigner = 8,
+    /// The voucher expiry is in the past
+    VoucherExpired = 9,
+    /// The voucher nonce was already redeemed
+    VoucherUsed = 10,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Campaign {
+    pub token: Address,
+    pub root: BytesN<32>,
+    /// Amount funded and not yet claimed
+    pub balance: i128,
+}
+
+pub(crate) fn check_admin(e: &Env, caller: &Address) -> Result<(), AirdropError> {
+    caller.require_auth();
+    let admin: Address = e.storage().instance().get(&DataKey::Admin).unwrap();
+    if *caller != admin {
+        return Err(AirdropError::Unauthorized);
+    }
+    Ok(())
+}
+
+fn leaf_hash(e: &Env, index: u32, to: &Address, amount: i128) -> BytesN<32> {
+    let mut leaf = Bytes::from_array(e, &index.to_be_bytes());
+    leaf.append(&to.clone().to_xdr(e));
+    leaf.extend_from_array(&amount.to_be_bytes());
+    e.crypto().sha256(&leaf).into()
+}
+
+fn verify_proof(e: &Env, root: &BytesN<32>, leaf: BytesN<32>, proof: Vec<BytesN<32>>) -> bool {
+    let mut node = leaf;
+    for sibling in proof.iter() {
+        let (first, second) = if node < sibling {
+            (node, sibling)
+        } else {
+            (sibling, node)
+        };
+        let mut pair = Bytes::from(first);
+        pair.append(&Bytes::from(second));
+        node = e.crypto().sha256(&pair).into();
+    }
+    node == *root
+}
+
+fn get_campaign(e: &Env, campaign: u32) -> Result<Campaign, AirdropError> {
+    let key = DataKey::Campaign(campaign);
+    let stored = e
+        .storage()
+        .persistent()
+        .get(&key)
+        .ok_or(AirdropError::CampaignNotFound)?;
+    ttl::extend_persistent(e, &key);
+    Ok(stored)
+}
+
+fn put_campaign(e: &Env, campaign: u32, stored: &Campaign) {
+    let key = DataKey::Campaign(campaign);
+    e.storage().persistent().set(&key, stored);
+    ttl::extend_persistent(e, &key);
+}
+
+fn get_claimed_word(e: &Env, campaign: u32, index: u32) -> u128 {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Claimed(campaign, index / WORD_BITS))
+        .unwrap_or(0)
+}
+
+contractmeta!(
+    key = "Description",
+    val = "Merkle airdrop and signed voucher campaigns, each paying from its own funded balance"
+);
+
+#[contract]
+pub struct Airdrop;
+
+#[contractimpl]
+impl Airdrop {
+    /// Initialize the airdrop contract with the admin who opens campaigns and
+    /// registers the voucher signer
+    pub fn __constructor(e: Env, admin: Address) {
+        e.storage().instance().set(&DataKey::Admin, &admin);
+        ttl::extend_instance(&e);
+    }
+
+    /// Admin function to open a campaign paying `token` to the leaves under
+    /// `root`. It pays nothing until funded. Returns the campaign id
+    pub fn create_campaign(
+        e: Env,
+        admin: Address,
+        token: Address,
+        root: BytesN<32>,
+    ) -> Result<u32, AirdropError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let campaign: u32 = e
+            .storage()
+            .instance()
+            .get(&DataKey::CampaignCount)
+            .unwrap_or(0);
+        e.storage()
+            .instance()
+            .set(&DataKey::CampaignCount, &(campaign + 1));
+        put_campaign(
+            &e,
+            campaign,
+            &Campaign {
+                token,
+                root,
+                balance: 0,
+            },
+        );
+
+        Ok(campaign)
+    }
+
+    /// Add `amount` of the campaign token, pulled from `from`, to the
+    /// balance `campaign` pays its claims from
+    pub fn fund_campaign(
+        e: Env,
+        from: Address,
+        campaign: u32,
+        amount: i128,
+    ) -> Result<(), AirdropError> {
+        from.require_auth();
+        ttl::extend_instance(&e);
+
+        if amount <= 0 {
+            return Err(AirdropError::InvalidAmount);
+        }
+        let mut stored = get_campaign(&e, campaign)?;
+        stored.balance = stored
+            .balance
+            .checked_add(amount)
+            .ok_or(AirdropError::Overflow)?;
+
+        soroban_sdk::token::Client::new(&e, &stored.token).transfer(
+            &from,
+            &e.current_contract_address(),
+            &amount,
+        );
+        put_campaign(&e, campaign, &stored);
+
+        events::campaign_funded(&e, from, campaign, amount);
+        Ok(())
+    }
+
+    /// Claim `amount` for leaf `index` of `campaign`
+    pub fn claim_with_proof(
+        e: Env,
+        to: Address,
+        campaign: u32,
+        index: u32,
+        amount: i128,
+        proof: Vec<BytesN<32>>,
+    ) -> Result<(), AirdropError> {
+        to.require_auth();
+        ttl::extend_instance(&e);
+
+        let mut stored = get_campaign(&e, campaign)?;
+        if amount <= 0 || !verify_proof(&e, &stored.root, leaf_hash(&e, index, &to, amount), proof)
+        {
+            return Err(AirdropError::InvalidProof);
+        }
+
+        let word = get_claimed_word(&e, campaign, index);
+        let bit = 1u128 << (index % WORD_BITS);
+        if word & bit != 0 {
+            return Err(AirdropError::AlreadyClaimed);
+        }
+
+        if stored.balance < amount {
+            return Err(AirdropError::CampaignUnderfunded);
+        }
+        stored.balance -= amount;
+        put_campaign(&e, campaign, &stored);
+
+        let claimed_key = DataKey::Claimed(campaign, index / WORD_BITS);
+        e.storage().persistent().set(&claimed_key, &(word | bit));
+        ttl::extend_persistent(&e, &claimed_key);
+        soroban_sdk::token::Client::new(&e, &stored.token).transfer(
+            &e.current_contract_address(),
+            &to,
+            &amount,
+        );
+
+        events::airdrop_claimed(&e, to, campaign, index, amount);
+        Ok(())
+    }
+
+    /// Admin function to close `campaign` and send its unspent balance to
+    /// `to`. A closed campaign pays no more claims. Returns the amount sent
+    pub fn close_campaign(
+        e: Env,
+        admin: Address,
+        campaign: u32,
+        to: Address,
+    ) -> Result<i128, AirdropError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let stored = get_campaign(&e, campaign)?;
+        e.storage()
+            .persistent()
+            .remove(&DataKey::Campaign(campaign));
+        if stored.balance > 0 {
+            soroban_sdk::token::Client::new(&e, &stored.token).transfer(
+                &e.current_contract_address(),
+                &to,
+                &stored.balance,
+            );
+        }
+
+        events::campaign_closed(&e, to, campaign, stored.balance);
+        Ok(stored.balance)
+    }
+
+    /// Get a campaign
+    pub fn get_campaign(e: Env, campaign: u32) -> Option<Campaign> {
+        get_campaign(&e, campaign).ok()
+    }
+
+    /// Check if leaf `index` of `campaign` was claimed
+    pub fn is_claimed(e: Env, campaign: u32, index: u32) -> bool {
+        get_claimed_word(&e, campaign, index) & (1u128 << (index % WORD_BITS)) != 0
+    }
+
+    /// Get admin address
+    pub fn get_admin(e: Env) -> Address {
+        e.storage().instance().get(&DataKey::Admin).unwrap()
+    }
+}
+
+mod test;
@@ -0,0 +1,403 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{Airdrop, AirdropClient, AirdropError, Campaign, Voucher, VoucherCampaign};
+use ed25519_dalek::{Signer, SigningKey};
+use soroban_sdk::{
+    symbol_short,
+    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger},
+    token, vec,
+    xdr::ToXdr,
+    Address, Bytes, BytesN, Env, IntoVal, Symbol, Val, Vec,
+};
+
+fn create_token_contract<'a>(
+    e: &Env,
+    admin: &Address,
+) -> (token::Client<'a>, token::StellarAssetClient<'a>) {
+    let sac = e.register_stellar_asset_contract_v2(admin.clone());
+    (
+        token::Client::new(e, &sac.address()),
+        token::StellarAssetClient::new(e, &sac.address()),
+    )
+}
+
+fn create_airdrop_contract<'a>(e: &Env, admin: &Address) -> AirdropClient<'a> {
+    AirdropClient::new(e, &e.register(Airdrop {}, (admin,)))
+}
+
+/// Events published by `contract` during the last invocation
+fn contract_events(e: &Env, contract: &Address) -> Vec<(Address, Vec<Val>, Val)> {
+    let mut events = Vec::new(e);
+    for event in e.events().all().iter() {
+        if event.0 == *contract {
+            events.push_back(event);
+        }
+    }
+    events
+}
+
+fn airdrop_leaf(e: &Env, index: u32, to: &Address, amount: i128) -> BytesN<32> {
+    let mut leaf = Bytes::from_array(e, &index.to_be_bytes());
+    leaf.append(&to.clone().to_xdr(e));
+    leaf.extend_from_array(&amount.to_be_bytes());
+    e.crypto().sha256(&leaf).into()
+}
+
+fn airdrop_node(e: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (first, second) = if a < b { (a, b) } else { (b, a) };
+    let mut pair = Bytes::from(first);
+    pair.append(&Bytes::from(second));
+    e.crypto().sha256(&pair).into()
+}
+
+#[test]
+fn test_merkle_airdrop() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let (token, token_admin) = create_token_contract(&e, &admin);
+    let airdrop = create_airdrop_contract(&e, &admin);
+
+    // Three attendees, the last leaf is paired with itself
+    let attendees = [
+        Address::generate(&e),
+        Address::generate(&e),
+        Address::generate(&e),
+    ];
+    let amounts = [100_i128, 250, 75];
+    let leaves: std::vec::Vec<BytesN<32>> = (0..3)
+        .map(|i| airdrop_leaf(&e, i as u32, &attendees[i], amounts[i]))
+        .collect();
+    let node01 = airdrop_node(&e, &leaves[0], &leaves[1]);
+    let node22 = airdrop_node(&e, &leaves[2], &leaves[2]);
+    let root = airdrop_node(&e, &node01, &node22);
+
+    let campaign = airdrop.create_campaign(&admin, &token.address, &root);
+    assert_eq!(campaign, 0);
+    assert_eq!(
+        airdrop.get_campaign(&campaign),
+        Some(Campaign {
+            token: token.address.clone(),
+            root: root.clone(),
+            balance: 0,
+        })
+    );
+
+    // Tokens sent to the contract outside of `fund_campaign` do not fund it
+    token_admin.mint(&airdrop.address, &1000);
+    let proof1 = vec![&e, leaves[0].clone(), node22.clone()];
+    assert_eq!(
+        airdrop.try_claim_with_proof(&attendees[1], &campaign, &1, &250, &proof1),
+        Err(Ok(AirdropError::CampaignUnderfunded))
+    );
+
+    let funder = Address::generate(&e);
+    token_admin.mint(&funder, &400);
+    airdrop.fund_campaign(&funder, &campaign, &300);
+    assert_eq!(
+        contract_events(&e, &airdrop.address),
+        vec![
+            &e,
+            (
+                airdrop.address.clone(),
+                (symbol_short!("camp_fund"), funder.clone(), campaign).into_val(&e),
+                300_i128.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(token.balance(&funder), 100);
+    assert_eq!(
+        airdrop.try_fund_campaign(&funder, &campaign, &0),
+        Err(Ok(AirdropError::InvalidAmount))
+    );
+
+    airdrop.claim_with_proof(&attendees[1], &campaign, &1, &250, &proof1);
+    assert_eq!(
+        contract_events(&e, &airdrop.address),
+        vec![
+            &e,
+            (
+                airdrop.address.clone(),
+                (symbol_short!("airdrop"), attendees[1].clone(), campaign).into_val(&e),
+                (1_u32, 250_i128).into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(token.balance(&attendees[1]), 250);
+    assert!(airdrop.is_claimed(&campaign, &1));
+    assert!(!airdrop.is_claimed(&campaign, &0));
+
+    assert_eq!(
+        airdrop.try_claim_with_proof(&attendees[1], &campaign, &1, &250, &proof1),
+        Err(Ok(AirdropError::AlreadyClaimed))
+    );
+
+    // Wrong amount, wrong claimer and wrong index all break the proof
+    let proof2 = vec![&e, leaves[2].clone(), node01.clone()];
+    assert_eq!(
+        airdrop.try_claim_with_proof(&attendees[2], &campaign, &2, &750, &proof2),
+        Err(Ok(AirdropError::InvalidProof))
+    );
+    assert_eq!(
+        airdrop.try_claim_with_proof(&attendees[0], &campaign, &2, &75, &proof2),
+        Err(Ok(AirdropError::InvalidProof))
+    );
+    assert_eq!(
+        airdrop.try_claim_with_proof(&attendees[2], &campaign, &0, &75, &proof2),
+        Err(Ok(AirdropError::InvalidProof))
+    );
+    // 50 left, below the last attendee's 75
+    assert_eq!(
+        airdrop.try_claim_with_proof(&attendees[2], &campaign, &2, &75, &proof2),
+        Err(Ok(AirdropError::CampaignUnderfunded))
+    );
+    airdrop.fund_campaign(&funder, &campaign, &100);
+    airdrop.claim_with_proof(&attendees[2], &campaign, &2, &75, &proof2);
+    assert_eq!(token.balance(&attendees[2]), 75);
+    assert_eq!(airdrop.get_campaign(&campaign).unwrap().balance, 75);
+
+    assert_eq!(
+        airdrop.try_claim_with_proof(&attendees[0], &1, &0, &100, &vec![&e]),
+        Err(Ok(AirdropError::CampaignNotFound))
+    );
+    assert_eq!(
+        airdrop.try_fund_campaign(&funder, &1, &100),
+        Err(Ok(AirdropError::CampaignNotFound))
+    );
+    assert_eq!(
+        airdrop.try_create_campaign(&attendees[0], &token.address, &root),
+        Err(Ok(AirdropError::Unauthorized))
+    );
+}
+
+fn sign_voucher(e: &Env, key: &SigningKey, airdrop: &Address, voucher: &Voucher) -> BytesN<64> {
+    let mut message = std::vec![0u8; 0];
+    for byte in (airdrop.clone(), voucher.clone()).to_xdr(e).iter() {
+        message.push(byte);
+    }
+    BytesN::from_array(e, &key.sign(&message).to_bytes())
+}
+
+#[test]
+fn test_voucher_claims() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let (token, token_admin) = create_token_contract(&e, &admin);
+    let airdrop = create_airdrop_contract(&e, &admin);
+    let campaign = airdrop.create_voucher_campaign(&admin, &token.address);
+    assert_eq!(
+        airdrop.get_voucher_campaign(&campaign),
+        Some(VoucherCampaign {
+            token: token.address.clone(),
+            balance: 0,
+        })
+    );
+
+    // Merkle campaign funds are not voucher funds
+    let merkle = airdrop.create_campaign(&admin, &token.address, &BytesN::from_array(&e, &[0; 32]));
+    token_admin.mint(&admin, &1000);
+    airdrop.fund_campaign(&admin, &merkle, &1000);
+
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let user = Address::generate(&e);
+    let voucher = Voucher {
+        campaign,
+        recipient: user.clone(),
+        amount: 40,
+        expiry: 1000,
+        nonce: 1,
+    };
+    let signature = sign_voucher(&e, &key, &airdrop.address, &voucher);
+
+    assert_eq!(
+        airdrop.try_claim_voucher(&voucher, &signature),
+        Err(Ok(AirdropError::NoVoucherSigner))
+    );
+
+    let signer = BytesN::from_array(&e, &key.verifying_key().to_bytes());
+    airdrop.set_voucher_signer(&admin, &Some(signer.clone()));
+    assert_eq!(airdrop.get_voucher_signer(), Some(signer));
+
+    assert_eq!(
+        airdrop.try_claim_voucher(&voucher, &signature),
+        Err(Ok(AirdropError::CampaignUnderfunded))
+    );
+    let funder = Address::generate(&e);
+    token_admin.mint(&funder, &50);
+    airdrop.fund_voucher_campaign(&funder, &campaign, &50);
+    assert_eq!(
+        contract_events(&e, &airdrop.address),
+        vec![
+            &e,
+            (
+                airdrop.address.clone(),
+                (symbol_short!("vch_fund"), funder.clone(), campaign).into_val(&e),
+                50_i128.into_val(&e)
+            ),
+        ]
+    );
+
+    airdrop.claim_voucher(&voucher, &signature);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            user.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    airdrop.address.clone(),
+                    Symbol::new(&e, "claim_voucher"),
+                    (voucher.clone(), signature.clone()).into_val(&e),
+                )),
+                sub_invocations: std::vec![],
+            }
+        )]
+    );
+    assert_eq!(
+        contract_events(&e, &airdrop.address),
+        vec![
+            &e,
+            (
+                airdrop.address.clone(),
+                (symbol_short!("voucher"), user.clone()).into_val(&e),
+                voucher.clone().into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(token.balance(&user), 40);
+    assert_eq!(airdrop.get_voucher_campaign(&campaign).unwrap().balance, 10);
+    assert!(airdrop.is_voucher_used(&1));
+
+    // Each nonce can be redeemed once
+    assert_eq!(
+        airdrop.try_claim_voucher(&voucher, &signature),
+        Err(Ok(AirdropError::VoucherUsed))
+    );
+
+    // Neither a tampered amount nor another recipient match the signature
+    let tampered = Voucher {
+        amount: 400,
+        nonce: 2,
+        ..voucher.clone()
+    };
+    assert!(airdrop.try_claim_voucher(&tampered, &signature).is_err());
+    let stolen = Voucher {
+        recipient: Address::generate(&e),
+        nonce: 2,
+        ..voucher.clone()
+    };
+    assert!(airdrop.try_claim_voucher(&stolen, &signature).is_err());
+    assert!(!airdrop.is_voucher_used(&2));
+
+    // The campaign only has 10 left
+    let large = Voucher {
+        amount: 11,
+        nonce: 3,
+        ..voucher.clone()
+    };
+    let large_signature = sign_voucher(&e, &key, &airdrop.address, &large);
+    assert_eq!(
+        airdrop.try_claim_voucher(&large, &large_signature),
+        Err(Ok(AirdropError::CampaignUnderfunded))
+    );
+
+    let late = Voucher {
+        nonce: 4,
+        ..voucher.clone()
+    };
+    let late_signature = sign_voucher(&e, &key, &airdrop.address, &late);
+    e.ledger().with_mut(|li| li.timestamp = 1001);
+    assert_eq!(
+        airdrop.try_claim_voucher(&late, &late_signature),
+        Err(Ok(AirdropError::VoucherExpired))
+    );
+
+    assert_eq!(
+        airdrop.try_fund_voucher_campaign(&funder, &(campaign + 1), &10),
+        Err(Ok(AirdropError::CampaignNotFound))
+    );
+    assert_eq!(
+        airdrop.try_create_voucher_campaign(&user, &token.address),
+        Err(Ok(AirdropError::Unauthorized))
+    );
+    assert_eq!(
+        airdrop.try_set_voucher_signer(&user, &None),
+        Err(Ok(AirdropError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_close_campaigns() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let (token, token_admin) = create_token_contract(&e, &admin);
+    let airdrop = create_airdrop_contract(&e, &admin);
+    let treasury = Address::generate(&e);
+
+    // A single attendee, whose leaf is the root
+    let attendee = Address::generate(&e);
+    let root = airdrop_leaf(&e, 0, &attendee, 100);
+    let campaign = airdrop.create_campaign(&admin, &token.address, &root);
+    let voucher_campaign = airdrop.create_voucher_campaign(&admin, &token.address);
+    token_admin.mint(&admin, &350);
+    airdrop.fund_campaign(&admin, &campaign, &300);
+    airdrop.fund_voucher_campaign(&admin, &voucher_campaign, &50);
+    airdrop.claim_with_proof(&attendee, &campaign, &0, &100, &vec![&e]);
+
+    assert_eq!(
+        airdrop.try_close_campaign(&attendee, &campaign, &attendee),
+        Err(Ok(AirdropError::Unauthorized))
+    );
+    assert_eq!(
+        airdrop.try_close_voucher_campaign(&attendee, &voucher_campaign, &attendee),
+        Err(Ok(AirdropError::Unauthorized))
+    );
+
+    // Only the unspent balance goes back
+    assert_eq!(airdrop.close_campaign(&admin, &campaign, &treasury), 200);
+    assert_eq!(
+        contract_events(&e, &airdrop.address),
+        vec![
+            &e,
+            (
+                airdrop.address.clone(),
+                (symbol_short!("camp_end"), treasury.clone(), campaign).into_val(&e),
+                200_i128.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(token.balance(&treasury), 200);
+    assert_eq!(airdrop.get_campaign(&campaign), None);
+    assert_eq!(
+        airdrop.try_fund_campaign(&admin, &campaign, &10),
+        Err(Ok(AirdropError::CampaignNotFound))
+    );
+
+    assert_eq!(
+        airdrop.close_voucher_campaign(&admin, &voucher_campaign, &treasury),
+        50
+    );
+    assert_eq!(
+        contract_events(&e, &airdrop.address),
+        vec![
+            &e,
+            (
+                airdrop.address.clone(),
+                (symbol_short!("vch_end"), treasury.clone(), voucher_campaign).into_val(&e),
+                50_i128.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(token.balance(&treasury), 250);
+    assert_eq!(token.balance(&airdrop.address), 0);
+    assert_eq!(airdrop.get_voucher_campaign(&voucher_campaign), None);
+    assert_eq!(
+        airdrop.try_close_voucher_campaign(&admin, &voucher_campaign, &treasury),
+        Err(Ok(AirdropError::CampaignNotFound))
+    );
+}
@@ -0,0 +1,28 @@
+//! Keeps the airdrop's storage from being archived. Every state-changing call
+//! extends the instance, and campaigns and claimed bitmaps are extended when a
+//! call touches them.
+
+use soroban_sdk::{Env, IntoVal, Val};
+
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// A week for the instance, which every call touches
+const INSTANCE_THRESHOLD: u32 = 6 * DAY_IN_LEDGERS;
+const INSTANCE_EXTEND_TO: u32 = 7 * DAY_IN_LEDGERS;
+
+/// A month for campaign entries, which may sit untouched between parties
+const PERSISTENT_THRESHOLD: u32 = 29 * DAY_IN_LEDGERS;
+const PERSISTENT_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+
+pub(crate) fn extend_instance(e: &Env) {
+    e.storage()
+        .instance()
+        .extend_ttl(INSTANCE_THRESHOLD, INSTANCE_EXTEND_TO);
+}
+
+/// Extends `key`, which must exist
+pub(crate) fn extend_persistent<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    e.storage()
+        .persistent()
+        .extend_ttl(key, PERSISTENT_THRESHOLD, PERSISTENT_EXTEND_TO);
+}
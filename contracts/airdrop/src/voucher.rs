@@ -0,0 +1,226 @@
+//! Signed vouchers: the organizer signs `(airdrop, voucher)` off-chain with
+//! an ed25519 key and hands it to its recipient, who redeems it once, e.g. by
+//! scanning a QR code at the door.
+//!
+//! The signed message is the XDR of the `(Address, Voucher)` tuple, so a
+//! voucher cannot be replayed against another contract, nor redeemed by
+//! anyone but the recipient it names. Vouchers pay from voucher campaigns,
+//! which hold their own funded balance apart from Merkle campaigns.
+
+use soroban_sdk::{contractimpl, contracttype, xdr::ToXdr, Address, BytesN, Env, Symbol};
+
+use crate::{check_admin, events, ttl, Airdrop, AirdropArgs, AirdropClient, AirdropError, DataKey};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct VoucherCampaign {
+    pub token: Address,
+    /// Amount funded and not yet redeemed, the most the campaign's vouchers
+    /// can still pay
+    pub balance: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Voucher {
+    /// Voucher campaign whose token is paid
+    pub campaign: u32,
+    /// The only account that can redeem the voucher
+    pub recipient: Address,
+    pub amount: i128,
+    /// Ledger timestamp after which the voucher can no longer be redeemed
+    pub expiry: u64,
+    pub nonce: u64,
+}
+
+fn get_voucher_signer(e: &Env) -> Option<BytesN<32>> {
+    e.storage().instance().get(&DataKey::VoucherSigner)
+}
+
+fn get_voucher_campaign(e: &Env, campaign: u32) -> Result<VoucherCampaign, AirdropError> {
+    let key = DataKey::VoucherCampaign(campaign);
+    let stored = e
+        .storage()
+        .persistent()
+        .get(&key)
+        .ok_or(AirdropError::CampaignNotFound)?;
+    ttl::extend_persistent(e, &key);
+    Ok(stored)
+}
+
+fn put_voucher_campaign(e: &Env, campaign: u32, stored: &VoucherCampaign) {
+    let key = DataKey::VoucherCampaign(campaign);
+    e.storage().persistent().set(&key, stored);
+    ttl::extend_persistent(e, &key);
+}
+
+#[contractimpl]
+impl Airdrop {
+    /// Admin function to register the ed25519 public key that signs
+    /// vouchers. `None` stops all voucher claims
+    pub fn set_voucher_signer(
+        e: Env,
+        admin: Address,
+        signer: Option<BytesN<32>>,
+    ) -> Result<(), AirdropError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let old_signer = get_voucher_signer(&e);
+        match &signer {
+            Some(signer) => e.storage().instance().set(&DataKey::VoucherSigner, signer),
+            None => e.storage().instance().remove(&DataKey::VoucherSigner),
+        }
+
+        events::config_changed(
+            &e,
+            Symbol::new(&e, "voucher_signer"),
+            admin,
+            old_signer,
+            signer,
+        );
+        Ok(())
+    }
+
+    /// Admin function to open a voucher campaign paying `token`. It pays
+    /// nothing until funded. Returns the campaign id
+    pub fn create_voucher_campaign(
+        e: Env,
+        admin: Address,
+        token: Address,
+    ) -> Result<u32, AirdropError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let campaign: u32 = e
+            .storage()
+            .instance()
+            .get(&DataKey::VoucherCampaignCount)
+            .unwrap_or(0);
+        e.storage()
+            .instance()
+            .set(&DataKey::VoucherCampaignCount, &(campaign + 1));
+        put_voucher_campaign(&e, campaign, &VoucherCampaign { token, balance: 0 });
+
+        Ok(campaign)
+    }
+
+    /// Add `amount` of the campaign token, pulled from `from`, to the
+    /// balance the vouchers of `campaign` are paid from
+    pub fn fund_voucher_campaign(
+        e: Env,
+        from: Address,
+        campaign: u32,
+        amount: i128,
+    ) -> Result<(), AirdropError> {
+        from.require_auth();
+        ttl::extend_instance(&e);
+
+        if amount <= 0 {
+            return Err(AirdropError::InvalidAmount);
+        }
+        let mut stored = get_voucher_campaign(&e, campaign)?;
+        stored.balance = stored
+            .balance
+            .checked_add(amount)
+            .ok_or(AirdropError::Overflow)?;
+
+        soroban_sdk::token::Client::new(&e, &stored.token).transfer(
+            &from,
+            &e.current_contract_address(),
+            &amount,
+        );
+        put_voucher_campaign(&e, campaign, &stored);
+
+        events::voucher_campaign_funded(&e, from, campaign, amount);
+        Ok(())
+    }
+
+    /// Redeem `voucher` to its recipient, who must authorize the call. Traps
+    /// if `signature` is not a valid signature of the voucher by the
+    /// registered key
+    pub fn claim_voucher(
+        e: Env,
+        voucher: Voucher,
+        signature: BytesN<64>,
+    ) -> Result<(), AirdropError> {
+        voucher.recipient.require_auth();
+        ttl::extend_instance(&e);
+
+        let signer = get_voucher_signer(&e).ok_or(AirdropError::NoVoucherSigner)?;
+        let message = (e.current_contract_address(), voucher.clone()).to_xdr(&e);
+        e.crypto().ed25519_verify(&signer, &message, &signature);
+
+        if e.ledger().timestamp() > voucher.expiry {
+            return Err(AirdropError::VoucherExpired);
+        }
+        let nonce_key = DataKey::VoucherNonce(voucher.nonce);
+        if e.storage().persistent().has(&nonce_key) {
+            return Err(AirdropError::VoucherUsed);
+        }
+        if voucher.amount <= 0 {
+            return Err(AirdropError::InvalidAmount);
+        }
+
+        let mut campaign = get_voucher_campaign(&e, voucher.campaign)?;
+        if campaign.balance < voucher.amount {
+            return Err(AirdropError::CampaignUnderfunded);
+        }
+        campaign.balance -= voucher.amount;
+        put_voucher_campaign(&e, voucher.campaign, &campaign);
+
+        e.storage().persistent().set(&nonce_key, &());
+        ttl::extend_persistent(&e, &nonce_key);
+        soroban_sdk::token::Client::new(&e, &campaign.token).transfer(
+            &e.current_contract_address(),
+            &voucher.recipient,
+            &voucher.amount,
+        );
+
+        events::voucher_redeemed(&e, voucher.recipient.clone(), voucher);
+        Ok(())
+    }
+
+    /// Admin function to close voucher campaign `campaign` and send its
+    /// unspent balance to `to`. Its vouchers can no longer be redeemed.
+    /// Returns the amount sent
+    pub fn close_voucher_campaign(
+        e: Env,
+        admin: Address,
+        campaign: u32,
+        to: Address,
+    ) -> Result<i128, AirdropError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let stored = get_voucher_campaign(&e, campaign)?;
+        e.storage()
+            .persistent()
+            .remove(&DataKey::VoucherCampaign(campaign));
+        if stored.balance > 0 {
+            soroban_sdk::token::Client::new(&e, &stored.token).transfer(
+                &e.current_contract_address(),
+                &to,
+                &stored.balance,
+            );
+        }
+
+        events::voucher_campaign_closed(&e, to, campaign, stored.balance);
+        Ok(stored.balance)
+    }
+
+    /// Get a voucher campaign
+    pub fn get_voucher_campaign(e: Env, campaign: u32) -> Option<VoucherCampaign> {
+        get_voucher_campaign(&e, campaign).ok()
+    }
+
+    /// Get the ed25519 public key that signs vouchers
+    pub fn get_voucher_signer(e: Env) -> Option<BytesN<32>> {
+        get_voucher_signer(&e)
+    }
+
+    /// Check if a voucher nonce was already redeemed
+    pub fn is_voucher_used(e: Env, nonce: u64) -> bool {
+        e.storage().persistent().has(&DataKey::VoucherNonce(nonce))
+    }
+}
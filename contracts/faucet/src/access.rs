@@ -0,0 +1,151 @@
+//! Access control. The owner (`DataKey::Admin`) holds every role, hands out
+//! the operator and funder roles and transfers ownership in two steps.
+
+use soroban_sdk::{contractimpl, contracttype, Address, Env, Symbol};
+
+use crate::{events, ttl, DataKey, Faucet, FaucetArgs, FaucetClient, FaucetError};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Role {
+    /// Manages the catalog, claim rules and the pause switch
+    Operator,
+    /// Adds pool liquidity on behalf of the faucet
+    Funder,
+}
+
+fn get_owner(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::Admin).unwrap()
+}
+
+fn get_pending_owner(e: &Env) -> Option<Address> {
+    e.storage().instance().get(&DataKey::PendingAdmin)
+}
+
+fn has_role(e: &Env, role: Role, account: &Address) -> bool {
+    if *account == get_owner(e) {
+        return true;
+    }
+    let key = DataKey::Role(role, account.clone());
+    let granted = e.storage().persistent().has(&key);
+    if granted {
+        ttl::extend_persistent(e, &key);
+    }
+    granted
+}
+
+pub(crate) fn require_owner(e: &Env, caller: &Address) -> Result<(), FaucetError> {
+    caller.require_auth();
+    if *caller != get_owner(e) {
+        return Err(FaucetError::Unauthorized);
+    }
+    Ok(())
+}
+
+pub(crate) fn require_role(e: &Env, caller: &Address, role: Role) -> Result<(), FaucetError> {
+    caller.require_auth();
+    if !has_role(e, role, caller) {
+        return Err(FaucetError::Unauthorized);
+    }
+    Ok(())
+}
+
+#[contractimpl]
+impl Faucet {
+    /// Owner function to start handing ownership to `new_admin`, who has to
+    /// call `accept_admin`. `None` cancels a pending transfer
+    pub fn propose_admin(
+        e: Env,
+        admin: Address,
+        new_admin: Option<Address>,
+    ) -> Result<(), FaucetError> {
+        require_owner(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let old_pending = get_pending_owner(&e);
+        match &new_admin {
+            Some(new_admin) => e
+                .storage()
+                .instance()
+                .set(&DataKey::PendingAdmin, new_admin),
+            None => e.storage().instance().remove(&DataKey::PendingAdmin),
+        }
+
+        events::config_changed(
+            &e,
+            Symbol::new(&e, "pending_admin"),
+            admin,
+            old_pending,
+            new_admin,
+        );
+        Ok(())
+    }
+
+    /// Complete an ownership transfer started by `propose_admin`
+    pub fn accept_admin(e: Env, new_admin: Address) -> Result<(), FaucetError> {
+        new_admin.require_auth();
+        ttl::extend_instance(&e);
+
+        let pending = get_pending_owner(&e).ok_or(FaucetError::NoPendingAdmin)?;
+        if new_admin != pending {
+            return Err(FaucetError::Unauthorized);
+        }
+
+        let old_admin = get_owner(&e);
+        e.storage().instance().set(&DataKey::Admin, &new_admin);
+        e.storage().instance().remove(&DataKey::PendingAdmin);
+
+        events::config_changed(
+            &e,
+            Symbol::new(&e, "admin"),
+            new_admin.clone(),
+            old_admin,
+            new_admin,
+        );
+        Ok(())
+    }
+
+    /// Owner function to give `role` to `account`
+    pub fn grant_role(
+        e: Env,
+        admin: Address,
+        role: Role,
+        account: Address,
+    ) -> Result<(), FaucetError> {
+        require_owner(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let key = DataKey::Role(role, account.clone());
+        e.storage().persistent().set(&key, &());
+        ttl::extend_persistent(&e, &key);
+        events::role_changed(&e, role, account, true);
+        Ok(())
+    }
+
+    /// Owner function to take `role` away from `account`
+    pub fn revoke_role(
+        e: Env,
+        admin: Address,
+        role: Role,
+        account: Address,
+    ) -> Result<(), FaucetError> {
+        require_owner(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        e.storage()
+            .persistent()
+            .remove(&DataKey::Role(role, account.clone()));
+        events::role_changed(&e, role, account, false);
+        Ok(())
+    }
+
+    /// Check if `account` holds `role`. The owner holds every role
+    pub fn has_role(e: Env, role: Role, account: Address) -> bool {
+        has_role(&e, role, &account)
+    }
+
+    /// Get the address ownership is being transferred to, if any
+    pub fn get_pending_admin(e: Env) -> Option<Address> {
+        get_pending_owner(&e)
+    }
+}
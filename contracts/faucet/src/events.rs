@@ -0,0 +1,90 @@
+use soroban_sdk::{contracttype, symbol_short, Address, BytesN, Env, IntoVal, Symbol, Val, Vec};
+
+use crate::Role;
+
+/// Data of the `("claim", to)` event
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ClaimEvent {
+    pub token: Address,
+    pub amount: i128,
+    pub shares_burned: i128,
+    /// Token A withdrawn from the pool and kept by the faucet
+    pub leftover_a: i128,
+    /// Token B withdrawn from the pool and kept by the faucet
+    pub leftover_b: i128,
+}
+
+pub(crate) fn claim(e: &Env, to: Address, event: ClaimEvent) {
+    e.events().publish((symbol_short!("claim"), to), event);
+}
+
+pub(crate) fn shares_deposited(e: &Env, sponsor: Address, share_amount: i128) {
+    e.events()
+        .publish((symbol_short!("sponsor"), sponsor), share_amount);
+}
+
+pub(crate) fn funded(e: &Env, from: Address, token: Address, amount: i128) {
+    e.events()
+        .publish((symbol_short!("fund"), from, token), amount);
+}
+
+/// Published as `("reclaim", to)` with `(shares_burned, amount_a, amount_b)`
+pub(crate) fn liquidity_reclaimed(
+    e: &Env,
+    to: Address,
+    shares_burned: i128,
+    amount_a: i128,
+    amount_b: i128,
+) {
+    e.events().publish(
+        (symbol_short!("reclaim"), to),
+        (shares_burned, amount_a, amount_b),
+    );
+}
+
+/// Published as `("sweep", token, to)` with the amount sent
+pub(crate) fn swept(e: &Env, token: Address, to: Address, amount: i128) {
+    e.events()
+        .publish((symbol_short!("sweep"), token, to), amount);
+}
+
+/// Published as `("allowlist", operator, added)` with the affected users
+pub(crate) fn allowlist_updated(e: &Env, operator: Address, added: bool, users: Vec<Address>) {
+    e.events()
+        .publish((symbol_short!("allowlist"), operator, added), users);
+}
+
+/// Published as `("role", role, account)` with whether the role was granted
+pub(crate) fn role_changed(e: &Env, role: Role, account: Address, granted: bool) {
+    e.events()
+        .publish((symbol_short!("role"), role, account), granted);
+}
+
+/// Published as `("paused", operator)` when payouts are stopped
+pub(crate) fn paused(e: &Env, operator: Address) {
+    e.events().publish((symbol_short!("paused"), operator), ());
+}
+
+/// Published as `("unpaused", operator)` when payouts resume
+pub(crate) fn unpaused(e: &Env, operator: Address) {
+    e.events()
+        .publish((symbol_short!("unpaused"), operator), ());
+}
+
+/// Published as `("upgrade", admin)` with the new wasm hash
+pub(crate) fn upgraded(e: &Env, admin: Address, new_wasm_hash: BytesN<32>) {
+    e.events()
+        .publish((symbol_short!("upgrade"), admin), new_wasm_hash);
+}
+
+/// Published by the setters as `("config_changed", field, caller)`
+/// with `(old, new)` as data
+pub(crate) fn config_changed<T>(e: &Env, field: Symbol, caller: Address, old: T, new: T)
+where
+    T: IntoVal<Env, Val>,
+{
+    let data: (Val, Val) = (old.into_val(e), new.into_val(e));
+    e.events()
+        .publish((Symbol::new(e, "config_changed"), field, caller), data);
+}
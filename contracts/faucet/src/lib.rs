@@ -1,30 +1,184 @@
 #![no_std]
 
-use soroban_sdk::{contract, contractimpl, contractmeta, contracttype, Address, Env};
+mod access;
+mod events;
+mod ttl;
+mod upgrade;
+
+use access::{require_owner, require_role};
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contracterror, contractimpl, contractmeta, contracttype, vec, Address, Env, IntoVal,
+    Map, Symbol, Vec,
+};
+
+pub use access::Role;
+pub use events::ClaimEvent;
+pub use ttl::TtlConfig;
 
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
     Admin,
     LiquidityPool,
-    FaucetAmount,
-    LastClaim(Address),
-    ClaimInterval,
-    WhichToken, // true = token_a, false = token_b
+    Assets,
+    LastClaim(Address, Address), // (user, token)
+    SponsoredShares(Address),
+    ClaimMode,
+    OpenToAll,
+    Allowlisted(Address),
+    LifetimeCap,
+    ClaimCount(Address),
+    Budget(Address),
+    BudgetSpent(Address),
+    Paused,
+    PendingAdmin,
+    Role(Role, Address),
+    Version,
+    TtlConfig,
+}
+
+/// Where `claim` takes the paid tokens from
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum FundingSource {
+    /// Withdraw LP shares the faucet holds in `LiquidityPool`
+    Pool,
+    /// Transfer the token from the faucet's own balance
+    Balance,
+}
+
+/// Cap on the total amount of a token paid by `claim` per epoch
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Budget {
+    pub max_amount: i128,
+    /// Epochs are aligned on multiples of this many seconds
+    pub epoch_length: u64,
+}
+
+/// Entry of the faucet catalog
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Asset {
+    pub token: Address,
+    /// Amount paid per claim
+    pub amount: i128,
+    /// Seconds a user has to wait between two claims of this token
+    pub interval: u64,
+    pub source: FundingSource,
+}
+
+/// How `claim` turns faucet-owned shares into the paid token
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ClaimMode {
+    /// Withdraw both tokens proportionally and keep the unpaid one idle
+    Proportional,
+    /// Sell the unpaid token back to the pool for more of the paid one, so
+    /// fewer shares are burned and nothing accumulates in the faucet
+    SwapBack,
+}
+
+/// Errors returned by the faucet. The numeric codes are part of the contract
+/// interface and must stay stable.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum FaucetError {
+    /// The faucet amount must be strictly positive
+    InvalidFaucetAmount = 1,
+    /// The caller is not the faucet admin
+    Unauthorized = 2,
+    /// The caller claimed less than `ClaimInterval` seconds ago
+    ClaimIntervalNotMet = 3,
+    /// The pool reserve of the distributed token is below the faucet amount
+    InsufficientPoolLiquidity = 4,
+    /// The faucet owns no shares in the pool
+    NoShares = 5,
+    /// The faucet owns fewer shares than a claim needs
+    InsufficientShares = 6,
+    /// The share transfer did not credit the faucet with the expected shares
+    ShareTransferFailed = 7,
+    /// The pool rejected the call because the caller lacks shares
+    PoolInsufficientShares = 8,
+    /// The pool rejected the call because a minimum amount was not met
+    PoolSlippage = 9,
+    /// The pool rejected the deposit amounts
+    PoolInvalidAmounts = 10,
+    /// The pool call failed for any other reason
+    PoolFailure = 11,
+    /// Share amounts must be strictly positive
+    InvalidShareAmount = 12,
+    /// The faucet's own token balance is below the faucet amount
+    InsufficientBalance = 13,
+    /// The call does not apply to the asset's funding source
+    WrongFundingSource = 14,
+    /// The token is not in the faucet catalog
+    AssetNotFound = 15,
+    /// A pool-funded asset must be one of the pool tokens
+    TokenNotInPool = 16,
+    /// The faucet is not open to all and the caller is not allowlisted
+    NotEligible = 17,
+    /// The caller already made as many claims as the lifetime cap allows
+    LifetimeCapReached = 18,
+    /// Budgets need a positive amount and epoch length
+    InvalidBudget = 19,
+    /// The token's budget for the current epoch is spent
+    BudgetExhausted = 20,
+    /// Claims and liquidity deposits are paused
+    Paused = 21,
+    /// The pool has paused swaps and deposits
+    PoolPaused = 22,
+    /// No ownership transfer is pending
+    NoPendingAdmin = 23,
+    /// A TTL threshold is above its extension or the maximum TTL
+    InvalidTtl = 24,
+    /// An amount does not fit in i128
+    Overflow = 25,
 }
 
 // Interface del Liquidity Pool
 mod liquidity_pool {
-    use soroban_sdk::{contractclient, Address, Env};
+    use soroban_sdk::{contractclient, contracterror, Address, Env};
 
+    /// Mirror of the pool's `PoolError` codes
+    #[contracterror]
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+    #[repr(u32)]
+    pub enum PoolError {
+        InvalidTokenOrder = 1,
+        InsufficientShares = 2,
+        AmountBBelowMin = 3,
+        AmountAInvalid = 4,
+        NonPositiveAmounts = 5,
+        InsufficientReserve = 6,
+        InMaxExceeded = 7,
+        InvariantViolated = 8,
+        NonPositiveReserves = 9,
+        MinNotSatisfied = 10,
+        OutBelowMin = 11,
+        Unauthorized = 12,
+        InvalidFee = 13,
+        NoFeeRecipient = 14,
+        NegativeAmount = 15,
+        InsufficientAllowance = 16,
+        InvalidExpiration = 17,
+        Paused = 18,
+        InvalidTtl = 19,
+        Overflow = 20,
+        InsufficientLiquidityMinted = 21,
+    }
+
+    #[allow(dead_code)]
     #[contractclient(name = "LiquidityPoolClient")]
     pub trait LiquidityPoolTrait {
         /// Get current reserves
         fn get_reserves(e: Env) -> (i128, i128);
-        
+
         /// Get token addresses
         fn get_tokens(e: Env) -> (Address, Address);
-        
+
         /// Withdraw liquidity from the pool
         fn withdraw(
             e: Env,
@@ -32,14 +186,36 @@ mod liquidity_pool {
             share_amount: i128,
             min_a: i128,
             min_b: i128,
-        ) -> (i128, i128);
-        
+        ) -> Result<(i128, i128), PoolError>;
+
         /// Get user's share balance
         fn balance_shares(e: Env, user: Address) -> i128;
-        
+
         /// Get total shares
         fn get_total_shares(e: Env) -> i128;
 
+        /// Transfer shares between addresses
+        fn transfer(e: Env, from: Address, to: Address, amount: i128) -> Result<(), PoolError>;
+
+        /// Quote a deposit as `(amount_a, amount_b, shares)`
+        fn quote_deposit(
+            e: Env,
+            desired_a: i128,
+            desired_b: i128,
+        ) -> Result<(i128, i128, i128), PoolError>;
+
+        /// Swap exactly `amount_in` of the sold token
+        fn swap_exact_in(
+            e: Env,
+            to: Address,
+            sell_a: bool,
+            amount_in: i128,
+            min_out: i128,
+        ) -> Result<i128, PoolError>;
+
+        /// Get the swap fee in basis points
+        fn get_fee(e: Env) -> u32;
+
         /// Deposit liquidity into the pool
         fn deposit(
             e: Env,
@@ -48,13 +224,387 @@ mod liquidity_pool {
             min_a: i128,
             desired_b: i128,
             min_b: i128,
-        );
+        ) -> Result<(), PoolError>;
+
+        /// Extend the pool instance and `user`'s share balance
+        fn bump(e: Env, user: Address);
+    }
+}
+
+/// Unwraps a `try_*` pool call, mapping pool failures to faucet errors
+fn pool_call<T, C, I>(
+    res: Result<Result<T, C>, Result<liquidity_pool::PoolError, I>>,
+) -> Result<T, FaucetError> {
+    use liquidity_pool::PoolError;
+
+    match res {
+        Ok(Ok(value)) => Ok(value),
+        Err(Ok(PoolError::InsufficientShares)) => Err(FaucetError::PoolInsufficientShares),
+        Err(Ok(PoolError::AmountBBelowMin))
+        | Err(Ok(PoolError::AmountAInvalid))
+        | Err(Ok(PoolError::MinNotSatisfied))
+        | Err(Ok(PoolError::OutBelowMin)) => Err(FaucetError::PoolSlippage),
+        Err(Ok(PoolError::NonPositiveAmounts))
+        | Err(Ok(PoolError::InsufficientLiquidityMinted)) => Err(FaucetError::PoolInvalidAmounts),
+        Err(Ok(PoolError::Paused)) => Err(FaucetError::PoolPaused),
+        _ => Err(FaucetError::PoolFailure),
+    }
+}
+
+/// Paid token the pool gives for selling it `other` of the other token,
+/// following the pool's rounding and fee. Zero when the sale buys nothing
+fn swap_back_out(
+    other: i128,
+    reserve_paid: i128,
+    reserve_other: i128,
+    fee_bps: u32,
+) -> Option<i128> {
+    if other <= 0 || reserve_paid <= 0 || reserve_other <= 0 {
+        return Some(0);
+    }
+
+    let other_after_fee = other.checked_mul(10_000 - fee_bps as i128)?;
+    let d = reserve_other
+        .checked_mul(10_000)?
+        .checked_add(other_after_fee)?;
+    wide_math::mul_div(other_after_fee, reserve_paid, d)
+}
+
+/// Paid token obtained by withdrawing `shares` and selling the other side
+/// back to the pool, following the pool's rounding and fee
+fn swap_back_amount(
+    shares: i128,
+    reserve_paid: i128,
+    reserve_other: i128,
+    total_shares: i128,
+    fee_bps: u32,
+) -> Option<i128> {
+    let paid = wide_math::mul_div(reserve_paid, shares, total_shares)?;
+    let other = wide_math::mul_div(reserve_other, shares, total_shares)?;
+    paid.checked_add(swap_back_out(
+        other,
+        reserve_paid - paid,
+        reserve_other - other,
+        fee_bps,
+    )?)
+}
+
+/// Fewest shares whose swap-back payout covers `amount`
+fn swap_back_shares(
+    amount: i128,
+    reserve_paid: i128,
+    reserve_other: i128,
+    total_shares: i128,
+    fee_bps: u32,
+) -> Option<i128> {
+    // A proportional withdrawal covers the amount on its own
+    let mut high = wide_math::mul_div_ceil(amount, total_shares, reserve_paid)?;
+    let mut low = 0;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if swap_back_amount(mid, reserve_paid, reserve_other, total_shares, fee_bps)? >= amount {
+            high = mid;
+        } else {
+            low = mid;
+        }
     }
+    Some(high)
+}
+
+fn get_claim_mode(e: &Env) -> ClaimMode {
+    e.storage()
+        .instance()
+        .get(&DataKey::ClaimMode)
+        .unwrap_or(ClaimMode::Proportional)
+}
+
+/// Pays `faucet_amount` of `token` to `to` by withdrawing faucet-owned shares
+/// from the pool
+fn pay_from_pool(
+    e: &Env,
+    to: &Address,
+    token: Address,
+    faucet_amount: i128,
+) -> Result<ClaimEvent, FaucetError> {
+    let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
+    let pool_client = liquidity_pool::LiquidityPoolClient::new(e, &pool_address);
+
+    let (token_a, token_b) = pool_client.get_tokens();
+    let which_token = pool_side(&token, &token_a, &token_b)?;
+
+    // Get current reserves to check availability
+    let (reserve_a, reserve_b) = pool_client.get_reserves();
+    let available_amount = if which_token { reserve_a } else { reserve_b };
+
+    if available_amount < faucet_amount {
+        return Err(FaucetError::InsufficientPoolLiquidity);
+    }
+
+    // Get faucet's share balance in the pool
+    let faucet_shares = pool_client.balance_shares(&e.current_contract_address());
+    let total_shares = pool_client.get_total_shares();
+
+    if faucet_shares == 0 {
+        return Err(FaucetError::NoShares);
+    }
+
+    // Calculate minimum shares needed to get the desired amount
+    let claim_mode = get_claim_mode(e);
+    let (target_reserve, other_reserve) = if which_token {
+        (reserve_a, reserve_b)
+    } else {
+        (reserve_b, reserve_a)
+    };
+    let fee_bps = match claim_mode {
+        ClaimMode::Proportional => 0,
+        ClaimMode::SwapBack => pool_client.get_fee(),
+    };
+    let shares_needed = match claim_mode {
+        // (faucet_amount * total_shares) / target_reserve, rounded up
+        ClaimMode::Proportional => {
+            wide_math::mul_div_ceil(faucet_amount, total_shares, target_reserve)
+        }
+        ClaimMode::SwapBack => swap_back_shares(
+            faucet_amount,
+            target_reserve,
+            other_reserve,
+            total_shares,
+            fee_bps,
+        ),
+    }
+    .ok_or(FaucetError::Overflow)?;
+
+    if shares_needed > faucet_shares {
+        return Err(FaucetError::InsufficientShares);
+    }
+
+    // Calculate minimum amounts (we want 0 of the token we don't need)
+    let (min_a, min_b) = match (claim_mode, which_token) {
+        (ClaimMode::SwapBack, _) => (0, 0),
+        (ClaimMode::Proportional, true) => (faucet_amount, 0), // At least faucet_amount of A
+        (ClaimMode::Proportional, false) => (0, faucet_amount), // At least faucet_amount of B
+    };
+
+    // Withdraw from pool to get the tokens
+    let faucet = e.current_contract_address();
+    let (received_a, received_b) =
+        pool_call(pool_client.try_withdraw(&faucet, &shares_needed, &min_a, &min_b))?;
+
+    let other_token = if which_token { token_b } else { token_a };
+    let (mut received, mut received_other) = if which_token {
+        (received_a, received_b)
+    } else {
+        (received_b, received_a)
+    };
+
+    // Sell the unwanted token back to the pool for more of the paid one.
+    // Dust that would buy nothing, which the pool rejects, is kept instead
+    let swap_out = match claim_mode {
+        ClaimMode::Proportional => 0,
+        ClaimMode::SwapBack => {
+            let (reserve_a, reserve_b) = pool_client.get_reserves();
+            let (reserve_paid, reserve_other) = if which_token {
+                (reserve_a, reserve_b)
+            } else {
+                (reserve_b, reserve_a)
+            };
+            swap_back_out(received_other, reserve_paid, reserve_other, fee_bps)
+                .ok_or(FaucetError::Overflow)?
+        }
+    };
+    if swap_out > 0 {
+        e.authorize_as_current_contract(vec![
+            e,
+            pool_transfer_auth(e, &other_token, &pool_address, received_other),
+        ]);
+        received += pool_call(pool_client.try_swap_exact_in(
+            &faucet,
+            &!which_token,
+            &received_other,
+            &(faucet_amount - received).max(0),
+        ))?;
+        received_other = 0;
+    }
+
+    // Transfer the desired token to the user
+    let token_client = soroban_sdk::token::Client::new(e, &token);
+    token_client.transfer(&faucet, to, &faucet_amount);
+
+    // If we got more than needed, keep the extra
+    // If we also got the other token, keep it until `sweep_token` returns it
+    let (leftover_a, leftover_b) = if which_token {
+        (received - faucet_amount, received_other)
+    } else {
+        (received_other, received - faucet_amount)
+    };
+
+    Ok(ClaimEvent {
+        token,
+        amount: faucet_amount,
+        shares_burned: shares_needed,
+        leftover_a,
+        leftover_b,
+    })
+}
+
+/// Pays `faucet_amount` of `token` to `to` from the faucet's own balance
+fn pay_from_balance(
+    e: &Env,
+    to: &Address,
+    token: Address,
+    faucet_amount: i128,
+) -> Result<ClaimEvent, FaucetError> {
+    let token_client = soroban_sdk::token::Client::new(e, &token);
+    if token_client.balance(&e.current_contract_address()) < faucet_amount {
+        return Err(FaucetError::InsufficientBalance);
+    }
+    token_client.transfer(&e.current_contract_address(), to, &faucet_amount);
+
+    Ok(ClaimEvent {
+        token,
+        amount: faucet_amount,
+        shares_burned: 0,
+        leftover_a: 0,
+        leftover_b: 0,
+    })
+}
+
+/// Whether `token` is token A (`true`) or token B (`false`) of the pool
+fn pool_side(token: &Address, token_a: &Address, token_b: &Address) -> Result<bool, FaucetError> {
+    if token == token_a {
+        Ok(true)
+    } else if token == token_b {
+        Ok(false)
+    } else {
+        Err(FaucetError::TokenNotInPool)
+    }
+}
+
+fn get_assets(e: &Env) -> Map<Address, Asset> {
+    e.storage()
+        .instance()
+        .get(&DataKey::Assets)
+        .unwrap_or(Map::new(e))
+}
+
+fn get_asset(e: &Env, token: &Address) -> Result<Asset, FaucetError> {
+    get_assets(e)
+        .get(token.clone())
+        .ok_or(FaucetError::AssetNotFound)
+}
+
+fn is_open_to_all(e: &Env) -> bool {
+    e.storage()
+        .instance()
+        .get(&DataKey::OpenToAll)
+        .unwrap_or(true)
+}
+
+fn is_allowlisted(e: &Env, user: &Address) -> bool {
+    let key = DataKey::Allowlisted(user.clone());
+    let allowlisted = e.storage().persistent().has(&key);
+    if allowlisted {
+        ttl::extend_persistent(e, &key);
+    }
+    allowlisted
+}
+
+fn get_lifetime_cap(e: &Env) -> Option<u32> {
+    e.storage().instance().get(&DataKey::LifetimeCap)
+}
+
+fn get_claim_count(e: &Env, user: &Address) -> u32 {
+    let key = DataKey::ClaimCount(user.clone());
+    match e.storage().persistent().get(&key) {
+        Some(count) => {
+            ttl::extend_persistent(e, &key);
+            count
+        }
+        None => 0,
+    }
+}
+
+fn get_budget(e: &Env, token: &Address) -> Option<Budget> {
+    e.storage().instance().get(&DataKey::Budget(token.clone()))
+}
+
+/// Remaining budget of `token` and the time the current epoch ends
+fn get_budget_status(e: &Env, token: &Address, budget: &Budget) -> (i128, u64) {
+    let now = e.ledger().timestamp();
+    let epoch_start = now - now % budget.epoch_length;
+    let spent = match e
+        .storage()
+        .instance()
+        .get::<_, (u64, i128)>(&DataKey::BudgetSpent(token.clone()))
+    {
+        Some((start, spent)) if start == epoch_start => spent,
+        _ => 0,
+    };
+    (budget.max_amount - spent, epoch_start + budget.epoch_length)
+}
+
+/// Takes `amount` out of the current epoch budget of `token`, if it has one
+fn spend_budget(e: &Env, token: &Address, amount: i128) -> Result<(), FaucetError> {
+    let Some(budget) = get_budget(e, token) else {
+        return Ok(());
+    };
+    let (remaining, resets_at) = get_budget_status(e, token, &budget);
+    if remaining < amount {
+        return Err(FaucetError::BudgetExhausted);
+    }
+
+    let epoch_start = resets_at - budget.epoch_length;
+    let spent = budget.max_amount - remaining + amount;
+    e.storage()
+        .instance()
+        .set(&DataKey::BudgetSpent(token.clone()), &(epoch_start, spent));
+    Ok(())
+}
+
+pub(crate) fn check_not_paused(e: &Env) -> Result<(), FaucetError> {
+    if e.storage()
+        .instance()
+        .get(&DataKey::Paused)
+        .unwrap_or(false)
+    {
+        return Err(FaucetError::Paused);
+    }
+    Ok(())
+}
+
+/// Checks the allowlist and the lifetime cap for `user`
+fn check_eligible(e: &Env, user: &Address) -> Result<(), FaucetError> {
+    if !is_open_to_all(e) && !is_allowlisted(e, user) {
+        return Err(FaucetError::NotEligible);
+    }
+    if let Some(cap) = get_lifetime_cap(e) {
+        if get_claim_count(e, user) >= cap {
+            return Err(FaucetError::LifetimeCapReached);
+        }
+    }
+    Ok(())
+}
+
+/// Auth entry allowing `pool` to move `amount` of `token` out of the faucet
+fn pool_transfer_auth(
+    e: &Env,
+    token: &Address,
+    pool: &Address,
+    amount: i128,
+) -> InvokerContractAuthEntry {
+    InvokerContractAuthEntry::Contract(SubContractInvocation {
+        context: ContractContext {
+            contract: token.clone(),
+            fn_name: Symbol::new(e, "transfer"),
+            args: (e.current_contract_address(), pool.clone(), amount).into_val(e),
+        },
+        sub_invocations: vec![e],
+    })
 }
 
 contractmeta!(
     key = "Description",
-    val = "Faucet contract that distributes tokens from a liquidity pool with rate limiting"
+    val = "Faucet contract that distributes a catalog of tokens with per-token rate limiting"
 );
 
 #[contract]
@@ -62,219 +612,399 @@ pub struct Faucet;
 
 #[contractimpl]
 impl Faucet {
-    /// Initialize the faucet contract
-    pub fn __constructor(
-        e: Env,
-        admin: Address,
-        liquidity_pool: Address,
-        faucet_amount: i128,
-        claim_interval: u64,
-        which_token: bool, // true = token_a, false = token_b
-    ) {
-        if faucet_amount <= 0 {
-            panic!("faucet amount must be positive");
-        }
-
+    /// Initialize the faucet contract. Dispensed tokens are added to the
+    /// catalog afterwards with `set_asset`
+    pub fn __constructor(e: Env, admin: Address, liquidity_pool: Address) {
         e.storage().instance().set(&DataKey::Admin, &admin);
-        e.storage().instance().set(&DataKey::LiquidityPool, &liquidity_pool);
-        e.storage().instance().set(&DataKey::FaucetAmount, &faucet_amount);
-        e.storage().instance().set(&DataKey::ClaimInterval, &claim_interval);
-        e.storage().instance().set(&DataKey::WhichToken, &which_token);
+        e.storage()
+            .instance()
+            .set(&DataKey::LiquidityPool, &liquidity_pool);
+        e.storage()
+            .instance()
+            .set(&DataKey::Version, &upgrade::SCHEMA_VERSION);
+        ttl::extend_instance(&e);
     }
 
-    /// Claim tokens from the liquidity pool
-    pub fn claim(e: Env, to: Address) {
+    /// Claim `token` from the liquidity pool or the faucet's own balance
+    pub fn claim(e: Env, to: Address, token: Address) -> Result<(), FaucetError> {
         to.require_auth();
+        ttl::extend_instance(&e);
+        check_not_paused(&e)?;
 
+        check_eligible(&e, &to)?;
+        let asset = get_asset(&e, &token)?;
         let current_time = e.ledger().timestamp();
-        let claim_interval: u64 = e.storage().instance().get(&DataKey::ClaimInterval).unwrap();
-        
-        // Check if enough time has passed since last claim
-        if let Some(last_claim_time) = e.storage().persistent().get::<DataKey, u64>(&DataKey::LastClaim(to.clone())) {
-            if current_time < last_claim_time + claim_interval {
-                panic!("claim interval not met");
+        let last_claim_key = DataKey::LastClaim(to.clone(), token.clone());
+
+        // Check if enough time has passed since last claim of this token
+        if let Some(last_claim_time) = e
+            .storage()
+            .persistent()
+            .get::<DataKey, u64>(&last_claim_key)
+        {
+            if current_time < last_claim_time.saturating_add(asset.interval) {
+                return Err(FaucetError::ClaimIntervalNotMet);
             }
         }
 
-        let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
-        let faucet_amount: i128 = e.storage().instance().get(&DataKey::FaucetAmount).unwrap();
-        let which_token: bool = e.storage().instance().get(&DataKey::WhichToken).unwrap();
+        spend_budget(&e, &token, asset.amount)?;
+        let event = match asset.source {
+            FundingSource::Pool => pay_from_pool(&e, &to, token, asset.amount)?,
+            FundingSource::Balance => pay_from_balance(&e, &to, token, asset.amount)?,
+        };
 
-        let pool_client = liquidity_pool::LiquidityPoolClient::new(&e, &pool_address);
-        
-        // Get current reserves to check availability
-        let (reserve_a, reserve_b) = pool_client.get_reserves();
-        let available_amount = if which_token { reserve_a } else { reserve_b };
-        
-        if available_amount < faucet_amount {
-            panic!("insufficient liquidity in pool");
-        }
+        // Update last claim time and the lifetime claim count
+        let claim_count_key = DataKey::ClaimCount(to.clone());
+        let claim_count = get_claim_count(&e, &to) + 1;
+        e.storage().persistent().set(&last_claim_key, &current_time);
+        e.storage().persistent().set(&claim_count_key, &claim_count);
+        ttl::extend_persistent(&e, &last_claim_key);
+        ttl::extend_persistent(&e, &claim_count_key);
 
-        // Get faucet's share balance in the pool
-        let faucet_shares = pool_client.balance_shares(&e.current_contract_address());
-        let total_shares = pool_client.get_total_shares();
-        
-        if faucet_shares == 0 {
-            panic!("faucet has no shares in the pool");
-        }
+        events::claim(&e, to, event);
 
-        // Calculate minimum shares needed to get the desired amount
-        // We need to withdraw enough shares to get at least faucet_amount of the desired token
-        let total_reserve = reserve_a + reserve_b;
-        let target_reserve = if which_token { reserve_a } else { reserve_b };
-        
-        // Calculate shares needed: (faucet_amount * total_shares) / target_reserve
-        // Add a small buffer to ensure we get enough
-        let shares_needed = (faucet_amount * total_shares * 101) / (target_reserve * 100);
-        
-        if shares_needed > faucet_shares {
-            panic!("faucet doesn't have enough shares");
-        }
+        Ok(())
+    }
 
-        // Calculate minimum amounts (we want 0 of the token we don't need)
-        let (min_a, min_b) = if which_token {
-            (faucet_amount, 0)  // We want at least faucet_amount of token A
-        } else {
-            (0, faucet_amount)  // We want at least faucet_amount of token B
-        };
+    /// Move LP shares from the admin or any sponsor into the faucet
+    pub fn deposit_shares(e: Env, from: Address, share_amount: i128) -> Result<(), FaucetError> {
+        from.require_auth();
+        ttl::extend_instance(&e);
 
-        // Withdraw from pool to get the tokens
-        let (received_a, received_b) = pool_client.withdraw(
-            &e.current_contract_address(),
-            &shares_needed,
-            &min_a,
-            &min_b
-        );
+        if share_amount <= 0 {
+            return Err(FaucetError::InvalidShareAmount);
+        }
 
-        // Get token addresses from pool
-        let (token_a, token_b) = pool_client.get_tokens();
-        
-        // Transfer the desired token to the user
-        if which_token {
-            // Transfer token A
-            let token_client = soroban_sdk::token::Client::new(&e, &token_a);
-            token_client.transfer(&e.current_contract_address(), &to, &faucet_amount);
-            
-            // If we got more than needed, keep the extra
-            // If we also got token B, keep it for future operations
-        } else {
-            // Transfer token B
-            let token_client = soroban_sdk::token::Client::new(&e, &token_b);
-            token_client.transfer(&e.current_contract_address(), &to, &faucet_amount);
-            
-            // If we got more than needed, keep the extra
-            // If we also got token A, keep it for future operations
+        let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
+        let pool_client = liquidity_pool::LiquidityPoolClient::new(&e, &pool_address);
+        let faucet = e.current_contract_address();
+
+        let shares_before = pool_client.balance_shares(&faucet);
+        pool_call(pool_client.try_transfer(&from, &faucet, &share_amount))?;
+        if pool_client.balance_shares(&faucet) != shares_before + share_amount {
+            return Err(FaucetError::ShareTransferFailed);
         }
 
-        // Update last claim time
-        e.storage().persistent().set(&DataKey::LastClaim(to.clone()), &current_time);
+        // Keep track of how many shares each sponsor has contributed
+        let key = DataKey::SponsoredShares(from.clone());
+        let sponsored: i128 = e.storage().persistent().get(&key).unwrap_or(0);
+        e.storage()
+            .persistent()
+            .set(&key, &(sponsored + share_amount));
+        ttl::extend_persistent(&e, &key);
+
+        events::shares_deposited(&e, from, share_amount);
+        Ok(())
     }
 
-    /// Admin function to deposit shares into the faucet
-    pub fn deposit_shares(e: Env, from: Address, share_amount: i128) {
+    /// Top up the faucet balance of a balance-funded asset
+    pub fn fund(e: Env, from: Address, token: Address, amount: i128) -> Result<(), FaucetError> {
         from.require_auth();
-        
-        let admin: Address = e.storage().instance().get(&DataKey::Admin).unwrap();
-        if from != admin {
-            panic!("only admin can deposit shares");
+        ttl::extend_instance(&e);
+
+        if get_asset(&e, &token)?.source != FundingSource::Balance {
+            return Err(FaucetError::WrongFundingSource);
+        }
+        if amount <= 0 {
+            return Err(FaucetError::InvalidFaucetAmount);
         }
 
-        let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
-        let pool_client = liquidity_pool::LiquidityPoolClient::new(&e, &pool_address);
-        
-        // This would need to be implemented if the pool supports share transfers
-        // For now, admin needs to deposit liquidity directly to the pool with faucet as recipient
-        panic!("use deposit_liquidity instead");
+        soroban_sdk::token::Client::new(&e, &token).transfer(
+            &from,
+            &e.current_contract_address(),
+            &amount,
+        );
+
+        events::funded(&e, from, token, amount);
+        Ok(())
     }
 
-    /// Admin function to deposit liquidity directly to the pool
-    /// This requires the admin to transfer tokens to this contract first
+    /// Funder function to deposit liquidity directly to the pool
+    /// The tokens are pulled from the funder and the shares go to the faucet
     pub fn deposit_liquidity(
-        e: Env, 
-        admin: Address,
+        e: Env,
+        funder: Address,
         desired_a: i128,
-        min_a: i128, 
+        min_a: i128,
         desired_b: i128,
-        min_b: i128
-    ) {
-        admin.require_auth();
-        
-        let stored_admin: Address = e.storage().instance().get(&DataKey::Admin).unwrap();
-        if admin != stored_admin {
-            panic!("unauthorized");
-        }
+        min_b: i128,
+    ) -> Result<(), FaucetError> {
+        require_role(&e, &funder, Role::Funder)?;
+        ttl::extend_instance(&e);
+        check_not_paused(&e)?;
 
         let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
         let pool_client = liquidity_pool::LiquidityPoolClient::new(&e, &pool_address);
-        
+
         // Get token addresses
         let (token_a, token_b) = pool_client.get_tokens();
-        
-        // Transfer tokens from admin to faucet
+
+        // Transfer tokens from funder to faucet
         let token_a_client = soroban_sdk::token::Client::new(&e, &token_a);
         let token_b_client = soroban_sdk::token::Client::new(&e, &token_b);
-        
-        token_a_client.transfer(&admin, &e.current_contract_address(), &desired_a);
-        token_b_client.transfer(&admin, &e.current_contract_address(), &desired_b);
-        
+
+        token_a_client.transfer(&funder, &e.current_contract_address(), &desired_a);
+        token_b_client.transfer(&funder, &e.current_contract_address(), &desired_b);
+
+        // The pool pulls the tokens from the faucet, so authorize those transfers
+        let (amount_a, amount_b, _) =
+            pool_call(pool_client.try_quote_deposit(&desired_a, &desired_b))?;
+        e.authorize_as_current_contract(vec![
+            &e,
+            pool_transfer_auth(&e, &token_a, &pool_address, amount_a),
+            pool_transfer_auth(&e, &token_b, &pool_address, amount_b),
+        ]);
+
         // Deposit to pool (this will give shares to the faucet contract)
-        pool_client.deposit(
+        pool_call(pool_client.try_deposit(
             &e.current_contract_address(),
             &desired_a,
             &min_a,
             &desired_b,
-            &min_b
+            &min_b,
+        ))?;
+
+        Ok(())
+    }
+
+    /// Owner function to withdraw `share_amount` faucet-owned shares from the
+    /// pool and send the tokens to `to`. Returns the amounts sent
+    pub fn reclaim_liquidity(
+        e: Env,
+        admin: Address,
+        share_amount: i128,
+        min_a: i128,
+        min_b: i128,
+        to: Address,
+    ) -> Result<(i128, i128), FaucetError> {
+        require_owner(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        if share_amount <= 0 {
+            return Err(FaucetError::InvalidShareAmount);
+        }
+
+        let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
+        let pool_client = liquidity_pool::LiquidityPoolClient::new(&e, &pool_address);
+        let faucet = e.current_contract_address();
+
+        let (amount_a, amount_b) =
+            pool_call(pool_client.try_withdraw(&faucet, &share_amount, &min_a, &min_b))?;
+
+        let (token_a, token_b) = pool_client.get_tokens();
+        soroban_sdk::token::Client::new(&e, &token_a).transfer(&faucet, &to, &amount_a);
+        soroban_sdk::token::Client::new(&e, &token_b).transfer(&faucet, &to, &amount_b);
+
+        events::liquidity_reclaimed(&e, to, share_amount, amount_a, amount_b);
+        Ok((amount_a, amount_b))
+    }
+
+    /// Owner function to send the faucet's whole balance of `token` to `to`.
+    /// Returns the amount sent
+    pub fn sweep_token(
+        e: Env,
+        admin: Address,
+        token: Address,
+        to: Address,
+    ) -> Result<i128, FaucetError> {
+        require_owner(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        let token_client = soroban_sdk::token::Client::new(&e, &token);
+        let amount = token_client.balance(&e.current_contract_address());
+        if amount > 0 {
+            token_client.transfer(&e.current_contract_address(), &to, &amount);
+        }
+
+        events::swept(&e, token, to, amount);
+        Ok(amount)
+    }
+
+    /// Operator function to add `asset` to the catalog or replace its entry
+    pub fn set_asset(e: Env, operator: Address, asset: Asset) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        if asset.amount <= 0 {
+            return Err(FaucetError::InvalidFaucetAmount);
+        }
+        if asset.source == FundingSource::Pool {
+            let (token_a, token_b) = Self::get_tokens(e.clone());
+            pool_side(&asset.token, &token_a, &token_b)?;
+        }
+
+        let mut assets = get_assets(&e);
+        let old_asset = assets.get(asset.token.clone());
+        assets.set(asset.token.clone(), asset.clone());
+        e.storage().instance().set(&DataKey::Assets, &assets);
+
+        events::config_changed(
+            &e,
+            Symbol::new(&e, "asset"),
+            operator,
+            old_asset,
+            Some(asset),
         );
+        Ok(())
     }
 
-    /// Admin function to set faucet amount
-    pub fn set_faucet_amount(e: Env, admin: Address, new_amount: i128) {
-        admin.require_auth();
-        
-        let stored_admin: Address = e.storage().instance().get(&DataKey::Admin).unwrap();
-        if admin != stored_admin {
-            panic!("unauthorized");
+    /// Operator function to stop dispensing `token`
+    pub fn remove_asset(e: Env, operator: Address, token: Address) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        let mut assets = get_assets(&e);
+        let old_asset = assets
+            .get(token.clone())
+            .ok_or(FaucetError::AssetNotFound)?;
+        assets.remove(token);
+        e.storage().instance().set(&DataKey::Assets, &assets);
+
+        events::config_changed(
+            &e,
+            Symbol::new(&e, "asset"),
+            operator,
+            Some(old_asset),
+            None,
+        );
+        Ok(())
+    }
+
+    /// Operator function to choose how claims are paid out
+    pub fn set_claim_mode(e: Env, operator: Address, mode: ClaimMode) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        let old_mode = get_claim_mode(&e);
+        e.storage().instance().set(&DataKey::ClaimMode, &mode);
+
+        events::config_changed(&e, Symbol::new(&e, "claim_mode"), operator, old_mode, mode);
+        Ok(())
+    }
+
+    /// Operator function to stop all payouts and liquidity deposits
+    pub fn pause(e: Env, operator: Address) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        e.storage().instance().set(&DataKey::Paused, &true);
+        events::paused(&e, operator);
+        Ok(())
+    }
+
+    /// Operator function to resume payouts and liquidity deposits
+    pub fn unpause(e: Env, operator: Address) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        e.storage().instance().set(&DataKey::Paused, &false);
+        events::unpaused(&e, operator);
+        Ok(())
+    }
+
+    /// Operator function to allow `users` to claim while the faucet is not open
+    /// to all
+    pub fn add_to_allowlist(
+        e: Env,
+        operator: Address,
+        users: Vec<Address>,
+    ) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        for user in users.iter() {
+            let key = DataKey::Allowlisted(user);
+            e.storage().persistent().set(&key, &());
+            ttl::extend_persistent(&e, &key);
         }
 
-        if new_amount <= 0 {
-            panic!("faucet amount must be positive");
+        events::allowlist_updated(&e, operator, true, users);
+        Ok(())
+    }
+
+    /// Operator function to remove `users` from the allowlist
+    pub fn remove_from_allowlist(
+        e: Env,
+        operator: Address,
+        users: Vec<Address>,
+    ) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        for user in users.iter() {
+            e.storage().persistent().remove(&DataKey::Allowlisted(user));
         }
 
-        e.storage().instance().set(&DataKey::FaucetAmount, &new_amount);
+        events::allowlist_updated(&e, operator, false, users);
+        Ok(())
+    }
+
+    /// Operator function to let any address claim (`true`) or only the
+    /// allowlisted ones (`false`)
+    pub fn set_open_to_all(e: Env, operator: Address, open: bool) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        let old_open = is_open_to_all(&e);
+        e.storage().instance().set(&DataKey::OpenToAll, &open);
+
+        events::config_changed(&e, Symbol::new(&e, "open_to_all"), operator, old_open, open);
+        Ok(())
     }
 
-    /// Admin function to set claim interval
-    pub fn set_claim_interval(e: Env, admin: Address, new_interval: u64) {
-        admin.require_auth();
-        
-        let stored_admin: Address = e.storage().instance().get(&DataKey::Admin).unwrap();
-        if admin != stored_admin {
-            panic!("unauthorized");
+    /// Operator function to cap how many claims an address can make over its
+    /// lifetime, across all assets. `None` removes the cap
+    pub fn set_lifetime_cap(
+        e: Env,
+        operator: Address,
+        cap: Option<u32>,
+    ) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        let old_cap = get_lifetime_cap(&e);
+        match cap {
+            Some(cap) => e.storage().instance().set(&DataKey::LifetimeCap, &cap),
+            None => e.storage().instance().remove(&DataKey::LifetimeCap),
         }
 
-        e.storage().instance().set(&DataKey::ClaimInterval, &new_interval);
+        events::config_changed(&e, Symbol::new(&e, "lifetime_cap"), operator, old_cap, cap);
+        Ok(())
     }
 
-    /// Admin function to change which token to distribute
-    pub fn set_token_type(e: Env, admin: Address, which_token: bool) {
-        admin.require_auth();
-        
-        let stored_admin: Address = e.storage().instance().get(&DataKey::Admin).unwrap();
-        if admin != stored_admin {
-            panic!("unauthorized");
+    /// Operator function to cap what `claim` pays of `token` per epoch. `None`
+    /// removes the cap
+    pub fn set_budget(
+        e: Env,
+        operator: Address,
+        token: Address,
+        budget: Option<Budget>,
+    ) -> Result<(), FaucetError> {
+        require_role(&e, &operator, Role::Operator)?;
+        ttl::extend_instance(&e);
+
+        let key = DataKey::Budget(token.clone());
+        let old_budget = get_budget(&e, &token);
+        match &budget {
+            Some(budget) if budget.max_amount <= 0 || budget.epoch_length == 0 => {
+                return Err(FaucetError::InvalidBudget);
+            }
+            Some(budget) => e.storage().instance().set(&key, budget),
+            None => e.storage().instance().remove(&key),
         }
 
-        e.storage().instance().set(&DataKey::WhichToken, &which_token);
+        events::config_changed(&e, Symbol::new(&e, "budget"), operator, old_budget, budget);
+        Ok(())
     }
 
-    /// Get current faucet amount
-    pub fn get_faucet_amount(e: Env) -> i128 {
-        e.storage().instance().get(&DataKey::FaucetAmount).unwrap()
+    /// Get the catalog entry of `token`
+    pub fn get_asset(e: Env, token: Address) -> Option<Asset> {
+        get_assets(&e).get(token)
     }
 
-    /// Get current claim interval
-    pub fn get_claim_interval(e: Env) -> u64 {
-        e.storage().instance().get(&DataKey::ClaimInterval).unwrap()
+    /// List every asset the faucet dispenses
+    pub fn list_assets(e: Env) -> Vec<Asset> {
+        get_assets(&e).values()
     }
 
     /// Get faucet's share balance in the pool
@@ -298,9 +1028,19 @@ impl Faucet {
         pool_client.get_tokens()
     }
 
-    /// Get which token is being distributed
-    pub fn get_token_type(e: Env) -> bool {
-        e.storage().instance().get(&DataKey::WhichToken).unwrap()
+    /// Get how claims are paid out
+    pub fn get_claim_mode(e: Env) -> ClaimMode {
+        get_claim_mode(&e)
+    }
+
+    /// Get token balances held idle by the faucet, outside of the pool
+    pub fn get_idle_balances(e: Env) -> (i128, i128) {
+        let (token_a, token_b) = Self::get_tokens(e.clone());
+        let faucet = e.current_contract_address();
+        (
+            soroban_sdk::token::Client::new(&e, &token_a).balance(&faucet),
+            soroban_sdk::token::Client::new(&e, &token_b).balance(&faucet),
+        )
     }
 
     /// Get liquidity pool address
@@ -313,51 +1053,123 @@ impl Faucet {
         e.storage().instance().get(&DataKey::Admin).unwrap()
     }
 
-    /// Get time until next claim is available for an address
-    pub fn time_until_next_claim(e: Env, user: Address) -> u64 {
+    /// Get total shares a sponsor has deposited into the faucet
+    pub fn get_sponsored_shares(e: Env, sponsor: Address) -> i128 {
+        e.storage()
+            .persistent()
+            .get(&DataKey::SponsoredShares(sponsor))
+            .unwrap_or(0)
+    }
+
+    /// Get time until `user` can claim `token` again
+    pub fn time_until_next_claim(
+        e: Env,
+        user: Address,
+        token: Address,
+    ) -> Result<u64, FaucetError> {
+        let asset = get_asset(&e, &token)?;
         let current_time = e.ledger().timestamp();
-        let claim_interval: u64 = e.storage().instance().get(&DataKey::ClaimInterval).unwrap();
-        
-        if let Some(last_claim_time) = e.storage().persistent().get::<DataKey, u64>(&DataKey::LastClaim(user)) {
-            let next_claim_time = last_claim_time + claim_interval;
-            if current_time >= next_claim_time {
-                0
-            } else {
-                next_claim_time - current_time
-            }
+
+        if let Some(last_claim_time) = e
+            .storage()
+            .persistent()
+            .get::<DataKey, u64>(&DataKey::LastClaim(user, token))
+        {
+            let next_claim_time = last_claim_time.saturating_add(asset.interval);
+            Ok(next_claim_time.saturating_sub(current_time))
         } else {
-            0
+            Ok(0)
         }
     }
 
-    /// Check if an address can claim now
-    pub fn can_claim(e: Env, user: Address) -> bool {
-        Self::time_until_next_claim(e, user) == 0
+    /// Check if an address can claim `token` now
+    pub fn can_claim(e: Env, user: Address, token: Address) -> bool {
+        let within_budget = match (get_asset(&e, &token), get_budget(&e, &token)) {
+            (Ok(asset), Some(budget)) => get_budget_status(&e, &token, &budget).0 >= asset.amount,
+            _ => true,
+        };
+        check_not_paused(&e).is_ok()
+            && within_budget
+            && check_eligible(&e, &user).is_ok()
+            && Self::time_until_next_claim(e, user, token) == Ok(0)
+    }
+
+    /// Get the per-epoch budget of `token`, if any
+    pub fn get_budget(e: Env, token: Address) -> Option<Budget> {
+        get_budget(&e, &token)
+    }
+
+    /// Get the budget of `token` left in the current epoch and the time the
+    /// epoch resets, or `None` if the token has no budget
+    pub fn get_budget_status(e: Env, token: Address) -> Option<(i128, u64)> {
+        let budget = get_budget(&e, &token)?;
+        Some(get_budget_status(&e, &token, &budget))
+    }
+
+    /// Check if payouts and liquidity deposits are paused
+    pub fn is_paused(e: Env) -> bool {
+        check_not_paused(&e).is_err()
+    }
+
+    /// Check if an address passes the allowlist and lifetime cap
+    pub fn is_eligible(e: Env, user: Address) -> bool {
+        check_eligible(&e, &user).is_ok()
+    }
+
+    /// Check if an address is on the allowlist
+    pub fn is_allowlisted(e: Env, user: Address) -> bool {
+        is_allowlisted(&e, &user)
+    }
+
+    /// Check if any address can claim, allowlisted or not
+    pub fn is_open_to_all(e: Env) -> bool {
+        is_open_to_all(&e)
     }
 
-    /// Get estimated available tokens for claims
-    pub fn get_available_for_claims(e: Env) -> i128 {
+    /// Get the lifetime claim cap per address, if any
+    pub fn get_lifetime_cap(e: Env) -> Option<u32> {
+        get_lifetime_cap(&e)
+    }
+
+    /// Get how many claims an address has made
+    pub fn get_claim_count(e: Env, user: Address) -> u32 {
+        get_claim_count(&e, &user)
+    }
+
+    /// Get estimated amount of `token` available for claims
+    pub fn get_available_for_claims(e: Env, token: Address) -> i128 {
+        let Ok(asset) = get_asset(&e, &token) else {
+            return 0;
+        };
+        if asset.source == FundingSource::Balance {
+            return soroban_sdk::token::Client::new(&e, &token)
+                .balance(&e.current_contract_address());
+        }
+
         let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
-        let which_token: bool = e.storage().instance().get(&DataKey::WhichToken).unwrap();
         let pool_client = liquidity_pool::LiquidityPoolClient::new(&e, &pool_address);
-        
+        let (token_a, token_b) = pool_client.get_tokens();
+        let Ok(which_token) = pool_side(&token, &token_a, &token_b) else {
+            return 0;
+        };
+
         let faucet_shares = pool_client.balance_shares(&e.current_contract_address());
         if faucet_shares == 0 {
             return 0;
         }
-        
+
         let (reserve_a, reserve_b) = pool_client.get_reserves();
         let total_shares = pool_client.get_total_shares();
-        
+
         if total_shares == 0 {
             return 0;
         }
-        
+
         // Estimate how much of the desired token we could get
         let target_reserve = if which_token { reserve_a } else { reserve_b };
-        
+
         // Conservative estimate: (faucet_shares * target_reserve) / total_shares
-        (faucet_shares * target_reserve) / total_shares
+        wide_math::mul_div(faucet_shares, target_reserve, total_shares).unwrap_or(0)
     }
 }
 
@@ -1,76 +1,38 @@
-// #![cfg(test)]
-// extern crate std;
-
-// use soroban_sdk::{Address, Env};
-
-// use crate::{FaucetClient, LiquidityPoolClient};
-// use crate::test::{create_liqpool_contract, create_token_contract};
-
-// #[test]
-// fn test_faucet_withdraws_and_pays() {
-//     let e = Env::default();
-//     e.mock_all_auths();
-
-//     let admin1 = Address::generate(&e);
-//     let admin2 = Address::generate(&e);
-//     let (token1, token1_admin) = create_token_contract(&e, &admin1);
-//     let (token2, token2_admin) = create_token_contract(&e, &admin2);
-
-//     let depositor = Address::generate(&e);
-//     token1_admin.mint(&depositor, &100);
-//     token2_admin.mint(&depositor, &100);
-
-//     let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
-//     let lp_client = LiquidityPoolClient::new(&e, &liqpool.address);
-//     lp_client.deposit(&depositor, &100, &100, &100, &100);
-
-//     // Faucet setup
-//     let faucet_id = e.register(crate::Faucet {}, ());
-//     let faucet = FaucetClient::new(&e, &faucet_id);
-
-//     // Transfer shares del pool al faucet
-//     let shares = lp_client.balance_shares(&depositor);
-//     assert_eq!(shares, 100);
-//     // Transferir manualmente las shares (usando acceso directo al storage)
-//     lp_client
-//         .env()
-//         .storage()
-//         .persistent()
-//         .set(&crate::DataKey::Shares(faucet_id.clone()), &shares);
-//     lp_client
-//         .env()
-//         .storage()
-//         .persistent()
-//         .set(&crate::DataKey::Shares(depositor.clone()), &0);
-//     lp_client
-//         .env()
-//         .storage()
-//         .instance()
-//         .set(&crate::DataKey::TotalShares, &shares);
-
-//     faucet.init(&liqpool.address, &token1.address, &1);
-
-//     let user1 = Address::generate(&e);
-//     faucet.pay_initial(&user1);
-
-//     assert_eq!(token1.balance(&user1), 1);
-// }
-
 #![cfg(test)]
 extern crate std;
 
-use crate::{Faucet, FaucetClient};
+use crate::{
+    Asset, Budget, ClaimEvent, ClaimMode, DataKey, Faucet, FaucetClient, FaucetError,
+    FundingSource, Role, TtlConfig,
+};
 use soroban_sdk::{
     symbol_short,
-    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, MockAuth, MockAuthInvoke},
-    token, Address, Env, IntoVal,
+    testutils::{
+        storage::{Instance as _, Persistent as _},
+        Address as _, Events, Ledger,
+    },
+    token, vec, Address, BytesN, Env, IntoVal, Symbol, Val, Vec,
 };
 
 // Import the LiquidityPool for testing
 mod liqpool {
-    soroban_sdk::contractimport!(
-        file = "../../target/wasm32v1-none/release/liquidity_pool.wasm"
-    );
+    soroban_sdk::contractimport!(file = "../../target/wasm32v1-none/release/liquidity_pool.wasm");
+}
+
+// The faucet as built by `stellar contract build`, to upgrade to. Like the
+// pool above it has to be built before the tests, which `make test` does
+mod faucet_wasm {
+    soroban_sdk::contractimport!(file = "../../target/wasm32v1-none/release/faucet.wasm");
+}
+
+// The Merkle airdrop, deployed next to the faucet with its own funds
+mod airdrop_wasm {
+    soroban_sdk::contractimport!(file = "../../target/wasm32v1-none/release/airdrop.wasm");
+}
+
+// The first upgradeable release of the faucet, pinned to upgrade from
+mod faucet_v1 {
+    soroban_sdk::contractimport!(file = "fixtures/faucet_v1.wasm");
 }
 
 fn create_token_contract<'a>(
@@ -89,24 +51,41 @@ fn create_liqpool_contract<'a>(
     token_a: &Address,
     token_b: &Address,
 ) -> liqpool::Client<'a> {
-    liqpool::Client::new(e, &e.register(liqpool::WASM, (token_a, token_b)))
+    liqpool::Client::new(
+        e,
+        &e.register(
+            liqpool::WASM,
+            (token_a, token_b, Address::generate(e), 30u32),
+        ),
+    )
 }
 
 fn create_faucet_contract<'a>(
     e: &Env,
     admin: &Address,
     liquidity_pool: &Address,
-    faucet_amount: i128,
-    claim_interval: u64,
-    which_token: bool,
 ) -> FaucetClient<'a> {
-    FaucetClient::new(
-        e,
-        &e.register(
-            Faucet {},
-            (admin, liquidity_pool, faucet_amount, claim_interval, which_token),
-        ),
-    )
+    FaucetClient::new(e, &e.register(Faucet {}, (admin, liquidity_pool)))
+}
+
+fn asset(token: &Address, amount: i128, interval: u64, source: FundingSource) -> Asset {
+    Asset {
+        token: token.clone(),
+        amount,
+        interval,
+        source,
+    }
+}
+
+/// Events published by `contract` during the last invocation
+fn contract_events(e: &Env, contract: &Address) -> Vec<(Address, Vec<Val>, Val)> {
+    let mut events = Vec::new(e);
+    for event in e.events().all().iter() {
+        if event.0 == *contract {
+            events.push_back(event);
+        }
+    }
+    events
 }
 
 #[test]
@@ -116,17 +95,12 @@ fn test_faucet_initialization() {
 
     let admin = Address::generate(&e);
     let liquidity_pool = Address::generate(&e);
-    let faucet_amount = 100i128;
-    let claim_interval = 3600u64; // 1 hour
-    let which_token = true; // token_a
 
-    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
 
-    assert_eq!(faucet.get_faucet_amount(), faucet_amount);
-    assert_eq!(faucet.get_claim_interval(), claim_interval);
     assert_eq!(faucet.get_admin(), admin);
     assert_eq!(faucet.get_pool_address(), liquidity_pool);
-    assert_eq!(faucet.get_token_type(), which_token);
+    assert_eq!(faucet.list_assets(), vec![&e]);
 }
 
 #[test]
@@ -137,15 +111,20 @@ fn test_faucet_claim_availability() {
     let admin = Address::generate(&e);
     let liquidity_pool = Address::generate(&e);
     let user = Address::generate(&e);
-    let faucet_amount = 100i128;
-    let claim_interval = 3600u64;
-    let which_token = true;
+    let token = Address::generate(&e);
+
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+    assert!(!faucet.can_claim(&user, &token));
+    assert_eq!(
+        faucet.try_time_until_next_claim(&user, &token),
+        Err(Ok(FaucetError::AssetNotFound))
+    );
 
-    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    faucet.set_asset(&admin, &asset(&token, 100, 3600, FundingSource::Balance));
 
     // User should be able to claim initially
-    assert_eq!(faucet.can_claim(&user), true);
-    assert_eq!(faucet.time_until_next_claim(&user), 0);
+    assert!(faucet.can_claim(&user, &token));
+    assert_eq!(faucet.time_until_next_claim(&user, &token), 0);
 }
 
 #[test]
@@ -155,59 +134,52 @@ fn test_admin_functions() {
 
     let admin = Address::generate(&e);
     let liquidity_pool = Address::generate(&e);
-    let faucet_amount = 100i128;
-    let claim_interval = 3600u64;
-    let which_token = true;
+    let token = Address::generate(&e);
 
-    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
 
-    // Test setting new faucet amount
-    let new_amount = 200i128;
-    faucet.set_faucet_amount(&admin, &new_amount);
-    assert_eq!(faucet.get_faucet_amount(), new_amount);
-
-    // Test setting new claim interval
-    let new_interval = 7200u64;
-    faucet.set_claim_interval(&admin, &new_interval);
-    assert_eq!(faucet.get_claim_interval(), new_interval);
-
-    // Test changing token type
-    let new_token_type = false;
-    faucet.set_token_type(&admin, &new_token_type);
-    assert_eq!(faucet.get_token_type(), new_token_type);
-}
-
-#[test]
-#[should_panic(expected = "faucet amount must be positive")]
-fn test_invalid_faucet_amount_initialization() {
-    let e = Env::default();
-    e.mock_all_auths();
+    // Test adding an asset
+    let slice = asset(&token, 100, 3600, FundingSource::Balance);
+    faucet.set_asset(&admin, &slice);
+    assert_eq!(faucet.get_asset(&token), Some(slice));
 
-    let admin = Address::generate(&e);
-    let liquidity_pool = Address::generate(&e);
-    let faucet_amount = -100i128; // Invalid negative amount
-    let claim_interval = 3600u64;
-    let which_token = true;
+    // Test replacing its amount and interval
+    let slice = asset(&token, 200, 7200, FundingSource::Balance);
+    faucet.set_asset(&admin, &slice);
+    assert_eq!(faucet.get_asset(&token), Some(slice.clone()));
+    assert_eq!(faucet.list_assets(), vec![&e, slice]);
 
-    create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    // Test removing it
+    faucet.remove_asset(&admin, &token);
+    assert_eq!(faucet.get_asset(&token), None);
+    assert_eq!(
+        faucet.try_remove_asset(&admin, &token),
+        Err(Ok(FaucetError::AssetNotFound))
+    );
 }
 
 #[test]
-#[should_panic(expected = "faucet amount must be positive")]
-fn test_invalid_faucet_amount_update() {
+fn test_invalid_faucet_amount() {
     let e = Env::default();
     e.mock_all_auths();
 
     let admin = Address::generate(&e);
     let liquidity_pool = Address::generate(&e);
-    let faucet_amount = 100i128;
-    let claim_interval = 3600u64;
-    let which_token = true;
+    let token = Address::generate(&e);
 
-    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+    faucet.set_asset(&admin, &asset(&token, 100, 3600, FundingSource::Balance));
 
-    // Try to set invalid amount
-    faucet.set_faucet_amount(&admin, &(-50i128));
+    // Try to set invalid amounts
+    assert_eq!(
+        faucet.try_set_asset(&admin, &asset(&token, -50, 3600, FundingSource::Balance)),
+        Err(Ok(FaucetError::InvalidFaucetAmount))
+    );
+    assert_eq!(
+        faucet.try_set_asset(&admin, &asset(&token, 0, 3600, FundingSource::Balance)),
+        Err(Ok(FaucetError::InvalidFaucetAmount))
+    );
+    assert_eq!(faucet.get_asset(&token).unwrap().amount, 100);
 }
 
 #[test]
@@ -220,52 +192,45 @@ fn test_faucet_pool_integration_basic() {
     let admin2 = Address::generate(&e);
     let (token1, token1_admin) = create_token_contract(&e, &admin1);
     let (token2, token2_admin) = create_token_contract(&e, &admin2);
-    
+
     // Create liquidity pool
     let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
-    
+
     // Setup users
     let lp_provider = Address::generate(&e);
     let faucet_admin = Address::generate(&e);
-    
+
     // Mint tokens to liquidity provider
     token1_admin.mint(&lp_provider, &10000);
     token2_admin.mint(&lp_provider, &10000);
-    
+
     // Add liquidity to pool
-    liqpool.deposit(&lp_provider, &1000, &1000, &1000, &1000);
-    
+    liqpool.deposit(&lp_provider, &2000, &2000, &2000, &2000);
+
     // Verify pool state
-    let (reserve_a, reserve_b) = liqpool.get_rsrvs();
-    assert_eq!(reserve_a, 1000);
-    assert_eq!(reserve_b, 1000);
-    
-    // Create faucet
-    let faucet_amount = 50i128;
-    let claim_interval = 3600u64;
-    let which_token = true; // distribute token_a
-    
-    let faucet = create_faucet_contract(
-        &e,
+    let (reserve_a, reserve_b) = liqpool.get_reserves();
+    assert_eq!(reserve_a, 2000);
+    assert_eq!(reserve_b, 2000);
+
+    // Create faucet distributing token_a
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
         &faucet_admin,
-        &liqpool.address,
-        faucet_amount,
-        claim_interval,
-        which_token,
+        &asset(&token1.address, 50, 3600, FundingSource::Pool),
     );
 
     // Verify faucet can read pool data
     let (pool_reserve_a, pool_reserve_b) = faucet.get_pool_reserves();
     assert_eq!(pool_reserve_a, reserve_a);
     assert_eq!(pool_reserve_b, reserve_b);
-    
+
     let (token_a_addr, token_b_addr) = faucet.get_tokens();
     assert_eq!(token_a_addr, token1.address);
     assert_eq!(token_b_addr, token2.address);
 
     // Initially faucet has no shares
     assert_eq!(faucet.get_share_balance(), 0);
-    assert_eq!(faucet.get_available_for_claims(), 0);
+    assert_eq!(faucet.get_available_for_claims(&token1.address), 0);
 }
 
 #[test]
@@ -278,55 +243,47 @@ fn test_faucet_with_liquidity_deposit() {
     let admin2 = Address::generate(&e);
     let (token1, token1_admin) = create_token_contract(&e, &admin1);
     let (token2, token2_admin) = create_token_contract(&e, &admin2);
-    
+
     // Create liquidity pool
     let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
-    
+
     // Setup users
     let lp_provider = Address::generate(&e);
     let faucet_admin = Address::generate(&e);
-    
+
     // Mint tokens to both liquidity provider and faucet admin
     token1_admin.mint(&lp_provider, &10000);
     token2_admin.mint(&lp_provider, &10000);
     token1_admin.mint(&faucet_admin, &5000);
     token2_admin.mint(&faucet_admin, &5000);
-    
+
     // Add initial liquidity to pool
-    liqpool.deposit(&lp_provider, &1000, &1000, &1000, &1000);
-    
-    // Create faucet
-    let faucet_amount = 50i128;
-    let claim_interval = 3600u64;
-    let which_token = true; // distribute token_a
-    
-    let faucet = create_faucet_contract(
-        &e,
+    liqpool.deposit(&lp_provider, &2000, &2000, &2000, &2000);
+
+    // Create faucet distributing token_a
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
         &faucet_admin,
-        &liqpool.address,
-        faucet_amount,
-        claim_interval,
-        which_token,
+        &asset(&token1.address, 50, 3600, FundingSource::Pool),
     );
 
     // Faucet admin deposits liquidity to give faucet shares
     faucet.deposit_liquidity(&faucet_admin, &500, &400, &500, &400);
-    
+
     // Now faucet should have shares and available tokens
     let faucet_shares = faucet.get_share_balance();
     assert!(faucet_shares > 0);
-    
-    let available_tokens = faucet.get_available_for_claims();
+
+    let available_tokens = faucet.get_available_for_claims(&token1.address);
     assert!(available_tokens > 0);
-    
+
     // Verify pool reserves increased
     let (reserve_a, reserve_b) = faucet.get_pool_reserves();
-    assert_eq!(reserve_a, 1500); // 1000 + 500
-    assert_eq!(reserve_b, 1500); // 1000 + 500
+    assert_eq!(reserve_a, 2500); // 2000 + 500
+    assert_eq!(reserve_b, 2500); // 2000 + 500
 }
 
 #[test]
-#[should_panic(expected = "faucet has no shares in the pool")]
 fn test_claim_without_shares() {
     let e = Env::default();
     e.mock_all_auths();
@@ -336,29 +293,29 @@ fn test_claim_without_shares() {
     let admin2 = Address::generate(&e);
     let (token1, token1_admin) = create_token_contract(&e, &admin1);
     let (token2, token2_admin) = create_token_contract(&e, &admin2);
-    
+
     // Create liquidity pool with some liquidity
     let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
     let lp_provider = Address::generate(&e);
-    
+
     token1_admin.mint(&lp_provider, &10000);
     token2_admin.mint(&lp_provider, &10000);
-    liqpool.deposit(&lp_provider, &1000, &1000, &1000, &1000);
-    
+    liqpool.deposit(&lp_provider, &2000, &2000, &2000, &2000);
+
     // Create faucet without giving it any shares
     let faucet_admin = Address::generate(&e);
-    let faucet = create_faucet_contract(
-        &e,
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
         &faucet_admin,
-        &liqpool.address,
-        50,
-        3600,
-        true,
+        &asset(&token1.address, 50, 3600, FundingSource::Pool),
     );
 
     // Try to claim - should fail because faucet has no shares
     let user = Address::generate(&e);
-    faucet.claim(&user);
+    assert_eq!(
+        faucet.try_claim(&user, &token1.address),
+        Err(Ok(FaucetError::NoShares))
+    );
 }
 
 #[test]
@@ -370,23 +327,26 @@ fn test_multiple_users_independent_claims() {
     let liquidity_pool = Address::generate(&e);
     let user1 = Address::generate(&e);
     let user2 = Address::generate(&e);
-    let faucet_amount = 100i128;
-    let claim_interval = 3600u64;
-    let which_token = true;
+    let (token, token_admin) = create_token_contract(&e, &admin);
 
-    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+    faucet.set_asset(
+        &admin,
+        &asset(&token.address, 100, 3600, FundingSource::Balance),
+    );
+    token_admin.mint(&faucet.address, &1000);
 
     // Both users should be able to claim initially
-    assert_eq!(faucet.can_claim(&user1), true);
-    assert_eq!(faucet.can_claim(&user2), true);
-    
+    assert!(faucet.can_claim(&user1, &token.address));
+    assert!(faucet.can_claim(&user2, &token.address));
+
     // Time restrictions should be independent for each user
-    assert_eq!(faucet.time_until_next_claim(&user1), 0);
-    assert_eq!(faucet.time_until_next_claim(&user2), 0);
+    faucet.claim(&user1, &token.address);
+    assert_eq!(faucet.time_until_next_claim(&user1, &token.address), 3600);
+    assert_eq!(faucet.time_until_next_claim(&user2, &token.address), 0);
 }
 
 #[test]
-#[should_panic(expected = "unauthorized")]
 fn test_non_admin_cannot_change_settings() {
     let e = Env::default();
     e.mock_all_auths();
@@ -394,14 +354,28 @@ fn test_non_admin_cannot_change_settings() {
     let admin = Address::generate(&e);
     let non_admin = Address::generate(&e);
     let liquidity_pool = Address::generate(&e);
-    let faucet_amount = 100i128;
-    let claim_interval = 3600u64;
-    let which_token = true;
+    let token = Address::generate(&e);
 
-    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+    faucet.set_asset(&admin, &asset(&token, 100, 3600, FundingSource::Balance));
 
-    // Non-admin tries to change faucet amount - should fail
-    faucet.set_faucet_amount(&non_admin, &200);
+    // Non-admin tries to change settings - should fail
+    assert_eq!(
+        faucet.try_set_asset(&non_admin, &asset(&token, 200, 0, FundingSource::Balance)),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.try_remove_asset(&non_admin, &token),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.try_set_claim_mode(&non_admin, &ClaimMode::SwapBack),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.try_deposit_liquidity(&non_admin, &1, &1, &1, &1),
+        Err(Ok(FaucetError::Unauthorized))
+    );
 }
 
 #[test]
@@ -409,22 +383,1214 @@ fn test_faucet_query_functions() {
     let e = Env::default();
     e.mock_all_auths();
 
-    let admin = Address::generate(&e);
-    let liquidity_pool = Address::generate(&e);
-    let faucet_amount = 100i128;
-    let claim_interval = 3600u64;
-    let which_token = false; // token_b
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, _) = create_token_contract(&e, &admin1);
+    let (token2, _) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
 
-    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool, faucet_amount, claim_interval, which_token);
+    let admin = Address::generate(&e);
+    let faucet = create_faucet_contract(&e, &admin, &liqpool.address);
+    let slice = asset(&token2.address, 100, 3600, FundingSource::Pool);
+    faucet.set_asset(&admin, &slice);
 
     // Test all getter functions
-    assert_eq!(faucet.get_faucet_amount(), faucet_amount);
-    assert_eq!(faucet.get_claim_interval(), claim_interval);
+    assert_eq!(faucet.get_asset(&token2.address), Some(slice.clone()));
+    assert_eq!(faucet.get_asset(&token1.address), None);
+    assert_eq!(faucet.list_assets(), vec![&e, slice]);
     assert_eq!(faucet.get_admin(), admin);
-    assert_eq!(faucet.get_pool_address(), liquidity_pool);
-    assert_eq!(faucet.get_token_type(), which_token);
-    
-    // These should return 0 initially since no real pool is connected
+    assert_eq!(faucet.get_pool_address(), liqpool.address);
+
+    // These should return 0 initially since the pool holds no liquidity
+    assert_eq!(faucet.get_share_balance(), 0);
+    assert_eq!(faucet.get_available_for_claims(&token2.address), 0);
+    assert_eq!(faucet.get_available_for_claims(&token1.address), 0);
+
+    // Only the pool tokens can be pool funded
+    let other = Address::generate(&e);
+    assert_eq!(
+        faucet.try_set_asset(&admin, &asset(&other, 100, 3600, FundingSource::Pool)),
+        Err(Ok(FaucetError::TokenNotInPool))
+    );
+}
+
+#[test]
+fn test_claim_interval_error() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &5000);
+    token2_admin.mint(&faucet_admin, &5000);
+
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token1.address, 50, 3600, FundingSource::Pool),
+    );
+    faucet.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+
+    let user = Address::generate(&e);
+    faucet.claim(&user, &token1.address);
+    assert_eq!(token1.balance(&user), 50);
+
+    e.ledger().with_mut(|li| li.timestamp += 3000);
+    assert_eq!(
+        faucet.try_claim(&user, &token1.address),
+        Err(Ok(FaucetError::ClaimIntervalNotMet))
+    );
+    assert_eq!(faucet.time_until_next_claim(&user, &token1.address), 600);
+
+    e.ledger().with_mut(|li| li.timestamp += 600);
+    faucet.claim(&user, &token1.address);
+    assert_eq!(token1.balance(&user), 100);
+
+    // An interval too long to add to the claim time means never again
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token1.address, 50, u64::MAX, FundingSource::Pool),
+    );
+    e.ledger().with_mut(|li| li.timestamp += 3600);
+    assert_eq!(
+        faucet.try_claim(&user, &token1.address),
+        Err(Ok(FaucetError::ClaimIntervalNotMet))
+    );
+    assert_eq!(
+        faucet.time_until_next_claim(&user, &token1.address),
+        u64::MAX - e.ledger().timestamp()
+    );
+}
+
+#[test]
+fn test_pool_errors_are_propagated() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &5000);
+    token2_admin.mint(&faucet_admin, &5000);
+
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    assert_eq!(
+        faucet.try_deposit_liquidity(&faucet_admin, &0, &0, &0, &0),
+        Err(Ok(FaucetError::PoolInvalidAmounts))
+    );
+
+    faucet.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+
+    // amount_b would be 500, below min_b
+    assert_eq!(
+        faucet.try_deposit_liquidity(&faucet_admin, &500, &0, &1000, &600),
+        Err(Ok(FaucetError::PoolSlippage))
+    );
+}
+
+#[test]
+fn test_deposit_shares_from_sponsor() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    // Any LP can sponsor the faucet, not just its admin
+    let sponsor = Address::generate(&e);
+    token1_admin.mint(&sponsor, &2000);
+    token2_admin.mint(&sponsor, &2000);
+    liqpool.deposit(&sponsor, &2000, &2000, &2000, &2000);
+
+    let faucet_admin = Address::generate(&e);
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token1.address, 50, 3600, FundingSource::Pool),
+    );
+
+    faucet.deposit_shares(&sponsor, &400);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (symbol_short!("sponsor"), sponsor.clone()).into_val(&e),
+                400_i128.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(faucet.get_share_balance(), 400);
+    assert_eq!(liqpool.balance_shares(&sponsor), 600);
+    assert_eq!(faucet.get_sponsored_shares(&sponsor), 400);
+
+    faucet.deposit_shares(&sponsor, &100);
+    assert_eq!(faucet.get_sponsored_shares(&sponsor), 500);
+
+    // The sponsored shares fund claims
+    let user = Address::generate(&e);
+    faucet.claim(&user, &token1.address);
+    assert_eq!(token1.balance(&user), 50);
+
+    assert_eq!(
+        faucet.try_deposit_shares(&sponsor, &0),
+        Err(Ok(FaucetError::InvalidShareAmount))
+    );
+    assert_eq!(
+        faucet.try_deposit_shares(&sponsor, &501),
+        Err(Ok(FaucetError::PoolInsufficientShares))
+    );
+}
+
+#[test]
+fn test_claim_event() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &5000);
+    token2_admin.mint(&faucet_admin, &5000);
+
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token1.address, 50, 3600, FundingSource::Pool),
+    );
+    faucet.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+
+    let user = Address::generate(&e);
+    faucet.claim(&user, &token1.address);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (symbol_short!("claim"), user.clone()).into_val(&e),
+                ClaimEvent {
+                    token: token1.address.clone(),
+                    amount: 50,
+                    shares_burned: 50,
+                    leftover_a: 0,
+                    leftover_b: 50,
+                }
+                .into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(token2.balance(&faucet.address), 50);
+    assert_eq!(faucet.get_idle_balances(), (0, 50));
+}
+
+#[test]
+fn test_config_changed_events() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let liquidity_pool = Address::generate(&e);
+    let token = Address::generate(&e);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+
+    let old_asset = asset(&token, 100, 3600, FundingSource::Balance);
+    faucet.set_asset(&admin, &old_asset);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (
+                    Symbol::new(&e, "config_changed"),
+                    Symbol::new(&e, "asset"),
+                    admin.clone()
+                )
+                    .into_val(&e),
+                (None::<Asset>, Some(old_asset.clone())).into_val(&e)
+            ),
+        ]
+    );
+
+    let new_asset = asset(&token, 200, 60, FundingSource::Balance);
+    faucet.set_asset(&admin, &new_asset);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (
+                    Symbol::new(&e, "config_changed"),
+                    Symbol::new(&e, "asset"),
+                    admin.clone()
+                )
+                    .into_val(&e),
+                (Some(old_asset), Some(new_asset.clone())).into_val(&e)
+            ),
+        ]
+    );
+
+    faucet.remove_asset(&admin, &token);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (
+                    Symbol::new(&e, "config_changed"),
+                    Symbol::new(&e, "asset"),
+                    admin.clone()
+                )
+                    .into_val(&e),
+                (Some(new_asset), None::<Asset>).into_val(&e)
+            ),
+        ]
+    );
+
+    faucet.set_claim_mode(&admin, &ClaimMode::SwapBack);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (
+                    Symbol::new(&e, "config_changed"),
+                    Symbol::new(&e, "claim_mode"),
+                    admin.clone()
+                )
+                    .into_val(&e),
+                (ClaimMode::Proportional, ClaimMode::SwapBack).into_val(&e)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_swap_back_claim_keeps_holdings_in_lp_form() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let lp_provider = Address::generate(&e);
+    token1_admin.mint(&lp_provider, &10000);
+    token2_admin.mint(&lp_provider, &10000);
+    liqpool.deposit(&lp_provider, &10000, &10000, &10000, &10000);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &1000);
+    token2_admin.mint(&faucet_admin, &1000);
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token2.address, 50, 3600, FundingSource::Pool),
+    );
+    faucet.deposit_liquidity(&faucet_admin, &1000, &1000, &1000, &1000);
+    assert_eq!(faucet.get_claim_mode(), ClaimMode::Proportional);
+
+    faucet.set_claim_mode(&faucet_admin, &ClaimMode::SwapBack);
+    assert_eq!(faucet.get_claim_mode(), ClaimMode::SwapBack);
+
+    let user = Address::generate(&e);
+    faucet.claim(&user, &token2.address);
+    assert_eq!(token2.balance(&user), 50);
+
+    // Roughly half the shares of a proportional claim are burned and the
+    // unpaid token goes back into the pool instead of sitting in the faucet
+    let shares_burned = 1000 - faucet.get_share_balance();
+    assert!(shares_burned > 25 && shares_burned < 50);
+    let (idle_a, idle_b) = faucet.get_idle_balances();
+    assert_eq!(idle_a, 0);
+    assert!(idle_b < 2);
+
+    let (reserve_a, reserve_b) = liqpool.get_reserves();
+    assert_eq!(token1.balance(&liqpool.address), reserve_a);
+    assert_eq!(token2.balance(&liqpool.address), reserve_b);
+
+    assert_eq!(
+        faucet.try_set_claim_mode(&user, &ClaimMode::Proportional),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_swap_back_claims_of_tiny_amounts() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let lp_provider = Address::generate(&e);
+    token1_admin.mint(&lp_provider, &10000);
+    token2_admin.mint(&lp_provider, &10000);
+    liqpool.deposit(&lp_provider, &10000, &10000, &10000, &10000);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &1000);
+    token2_admin.mint(&faucet_admin, &1000);
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.deposit_liquidity(&faucet_admin, &1000, &1000, &1000, &1000);
+    faucet.set_claim_mode(&faucet_admin, &ClaimMode::SwapBack);
+
+    // Selling a single unit back to the pool buys nothing, so the faucet
+    // keeps it instead of having the swap rejected
+    for amount in 1..=3 {
+        faucet.set_asset(
+            &faucet_admin,
+            &asset(&token2.address, amount, 3600, FundingSource::Pool),
+        );
+        let user = Address::generate(&e);
+        faucet.claim(&user, &token2.address);
+        assert_eq!(token2.balance(&user), amount);
+    }
+
+    let (idle_a, _) = faucet.get_idle_balances();
+    assert!(idle_a > 0);
+}
+
+#[test]
+fn test_balance_funded_faucet() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let (token, token_admin) = create_token_contract(&e, &admin);
+    let liquidity_pool = Address::generate(&e);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+
+    let sponsor = Address::generate(&e);
+    token_admin.mint(&sponsor, &1000);
+    assert_eq!(
+        faucet.try_fund(&sponsor, &token.address, &150),
+        Err(Ok(FaucetError::AssetNotFound))
+    );
+
+    faucet.set_asset(
+        &admin,
+        &asset(&token.address, 100, 3600, FundingSource::Balance),
+    );
+
+    assert_eq!(
+        faucet.try_fund(&sponsor, &token.address, &0),
+        Err(Ok(FaucetError::InvalidFaucetAmount))
+    );
+    faucet.fund(&sponsor, &token.address, &150);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (
+                    symbol_short!("fund"),
+                    sponsor.clone(),
+                    token.address.clone()
+                )
+                    .into_val(&e),
+                150_i128.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(faucet.get_available_for_claims(&token.address), 150);
+
+    // Claims are paid straight from the balance, no pool involved
+    let user1 = Address::generate(&e);
+    faucet.claim(&user1, &token.address);
+    assert_eq!(token.balance(&user1), 100);
+    assert_eq!(token.balance(&faucet.address), 50);
+    assert_eq!(
+        faucet.try_claim(&user1, &token.address),
+        Err(Ok(FaucetError::ClaimIntervalNotMet))
+    );
+
+    let user2 = Address::generate(&e);
+    assert_eq!(
+        faucet.try_claim(&user2, &token.address),
+        Err(Ok(FaucetError::InsufficientBalance))
+    );
+    assert!(faucet.can_claim(&user2, &token.address));
+}
+
+#[test]
+fn test_balance_claims_leave_airdrop_funds_alone() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let liquidity_pool = Address::generate(&e);
+    let (token, token_admin) = create_token_contract(&e, &admin);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+
+    // A campaign funded with the token the faucet also pays from its balance
+    let airdrop = airdrop_wasm::Client::new(&e, &e.register(airdrop_wasm::WASM, (&admin,)));
+    let campaign =
+        airdrop.create_campaign(&admin, &token.address, &BytesN::from_array(&e, &[0; 32]));
+    token_admin.mint(&admin, &1000);
+    airdrop.fund_campaign(&admin, &campaign, &1000);
+    faucet.set_asset(
+        &admin,
+        &asset(&token.address, 100, 3600, FundingSource::Balance),
+    );
+
+    let user = Address::generate(&e);
+    assert_eq!(faucet.get_available_for_claims(&token.address), 0);
+    assert_eq!(
+        faucet.try_claim(&user, &token.address),
+        Err(Ok(FaucetError::InsufficientBalance))
+    );
+
+    token_admin.mint(&admin, &100);
+    faucet.fund(&admin, &token.address, &100);
+    faucet.claim(&user, &token.address);
+    assert_eq!(token.balance(&user), 100);
+    assert_eq!(token.balance(&airdrop.address), 1000);
+    assert_eq!(airdrop.get_campaign(&campaign).unwrap().balance, 1000);
+
+    // Nor can sweeping the faucet reach them
+    token_admin.mint(&faucet.address, &30);
+    assert_eq!(faucet.sweep_token(&admin, &token.address, &admin), 30);
+    assert_eq!(token.balance(&airdrop.address), 1000);
+}
+
+#[test]
+fn test_catalog_of_assets() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (slice, slice_admin) = create_token_contract(&e, &admin1);
+    let (bebida, bebida_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &slice.address, &bebida.address);
+
+    let faucet_admin = Address::generate(&e);
+    let (merch, merch_admin) = create_token_contract(&e, &faucet_admin);
+    slice_admin.mint(&faucet_admin, &2000);
+    bebida_admin.mint(&faucet_admin, &2000);
+    merch_admin.mint(&faucet_admin, &10);
+
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&slice.address, 50, 3600, FundingSource::Pool),
+    );
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&bebida.address, 20, 600, FundingSource::Pool),
+    );
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&merch.address, 1, 86400, FundingSource::Balance),
+    );
+    assert_eq!(faucet.list_assets().len(), 3);
+
+    // Assets with the wrong funding source cannot be topped up
+    assert_eq!(
+        faucet.try_fund(&faucet_admin, &slice.address, &10),
+        Err(Ok(FaucetError::WrongFundingSource))
+    );
+    faucet.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+    faucet.fund(&faucet_admin, &merch.address, &10);
+
+    // Every token has its own amount and rate limit
+    let user = Address::generate(&e);
+    faucet.claim(&user, &slice.address);
+    faucet.claim(&user, &bebida.address);
+    faucet.claim(&user, &merch.address);
+    assert_eq!(slice.balance(&user), 50);
+    assert_eq!(bebida.balance(&user), 20);
+    assert_eq!(merch.balance(&user), 1);
+
+    e.ledger().with_mut(|li| li.timestamp += 600);
+    faucet.claim(&user, &bebida.address);
+    assert_eq!(bebida.balance(&user), 40);
+    assert_eq!(
+        faucet.try_claim(&user, &slice.address),
+        Err(Ok(FaucetError::ClaimIntervalNotMet))
+    );
+    assert_eq!(faucet.time_until_next_claim(&user, &slice.address), 3000);
+    assert_eq!(faucet.time_until_next_claim(&user, &merch.address), 85800);
+
+    // Removed assets can no longer be claimed
+    faucet.remove_asset(&faucet_admin, &merch.address);
+    e.ledger().with_mut(|li| li.timestamp += 86400);
+    assert_eq!(
+        faucet.try_claim(&user, &merch.address),
+        Err(Ok(FaucetError::AssetNotFound))
+    );
+    assert_eq!(faucet.list_assets().len(), 2);
+}
+
+#[test]
+fn test_allowlist_and_lifetime_cap() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let liquidity_pool = Address::generate(&e);
+    let (token, token_admin) = create_token_contract(&e, &admin);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+    faucet.set_asset(
+        &admin,
+        &asset(&token.address, 10, 0, FundingSource::Balance),
+    );
+    token_admin.mint(&faucet.address, &1000);
+
+    let attendee1 = Address::generate(&e);
+    let attendee2 = Address::generate(&e);
+    let bot = Address::generate(&e);
+
+    // Open to all by default
+    assert!(faucet.is_open_to_all());
+    assert!(faucet.is_eligible(&bot));
+
+    faucet.set_open_to_all(&admin, &false);
+    faucet.add_to_allowlist(&admin, &vec![&e, attendee1.clone(), attendee2.clone()]);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (symbol_short!("allowlist"), admin.clone(), true).into_val(&e),
+                vec![&e, attendee1.clone(), attendee2.clone()].into_val(&e)
+            ),
+        ]
+    );
+    assert!(faucet.is_allowlisted(&attendee1));
+    assert!(!faucet.is_eligible(&bot));
+    assert!(!faucet.can_claim(&bot, &token.address));
+    assert_eq!(
+        faucet.try_claim(&bot, &token.address),
+        Err(Ok(FaucetError::NotEligible))
+    );
+
+    faucet.claim(&attendee1, &token.address);
+    assert_eq!(token.balance(&attendee1), 10);
+
+    faucet.remove_from_allowlist(&admin, &vec![&e, attendee2.clone()]);
+    assert!(!faucet.is_eligible(&attendee2));
+
+    // The cap counts claims over the address lifetime
+    faucet.set_lifetime_cap(&admin, &Some(2));
+    assert_eq!(faucet.get_lifetime_cap(), Some(2));
+    faucet.claim(&attendee1, &token.address);
+    assert_eq!(faucet.get_claim_count(&attendee1), 2);
+    assert!(!faucet.is_eligible(&attendee1));
+    assert_eq!(
+        faucet.try_claim(&attendee1, &token.address),
+        Err(Ok(FaucetError::LifetimeCapReached))
+    );
+
+    faucet.set_lifetime_cap(&admin, &None);
+    faucet.set_open_to_all(&admin, &true);
+    faucet.claim(&attendee1, &token.address);
+    faucet.claim(&bot, &token.address);
+    assert_eq!(token.balance(&bot), 10);
+
+    assert_eq!(
+        faucet.try_add_to_allowlist(&bot, &vec![&e, bot.clone()]),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.try_set_open_to_all(&bot, &true),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.try_set_lifetime_cap(&bot, &None),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_epoch_budget() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.ledger().with_mut(|li| li.timestamp = 100_000);
+
+    let admin = Address::generate(&e);
+    let liquidity_pool = Address::generate(&e);
+    let (token, token_admin) = create_token_contract(&e, &admin);
+    let faucet = create_faucet_contract(&e, &admin, &liquidity_pool);
+    faucet.set_asset(
+        &admin,
+        &asset(&token.address, 40, 3600, FundingSource::Balance),
+    );
+    token_admin.mint(&faucet.address, &1000);
+    assert_eq!(faucet.get_budget_status(&token.address), None);
+
+    assert_eq!(
+        faucet.try_set_budget(
+            &admin,
+            &token.address,
+            &Some(Budget {
+                max_amount: 100,
+                epoch_length: 0
+            })
+        ),
+        Err(Ok(FaucetError::InvalidBudget))
+    );
+    let budget = Budget {
+        max_amount: 100,
+        epoch_length: 86400,
+    };
+    faucet.set_budget(&admin, &token.address, &Some(budget.clone()));
+    assert_eq!(faucet.get_budget(&token.address), Some(budget));
+
+    // 100_000 falls in the epoch [86_400, 172_800)
+    assert_eq!(
+        faucet.get_budget_status(&token.address),
+        Some((100, 172_800))
+    );
+
+    let users = [
+        Address::generate(&e),
+        Address::generate(&e),
+        Address::generate(&e),
+    ];
+    faucet.claim(&users[0], &token.address);
+    faucet.claim(&users[1], &token.address);
+    assert_eq!(
+        faucet.get_budget_status(&token.address),
+        Some((20, 172_800))
+    );
+    assert!(!faucet.can_claim(&users[2], &token.address));
+    assert_eq!(
+        faucet.try_claim(&users[2], &token.address),
+        Err(Ok(FaucetError::BudgetExhausted))
+    );
+
+    // The budget resets with the next epoch
+    e.ledger().with_mut(|li| li.timestamp = 172_800);
+    assert_eq!(
+        faucet.get_budget_status(&token.address),
+        Some((100, 259_200))
+    );
+    faucet.claim(&users[2], &token.address);
+    assert_eq!(token.balance(&users[2]), 40);
+
+    faucet.set_budget(&admin, &token.address, &None);
+    assert_eq!(faucet.get_budget_status(&token.address), None);
+
+    assert_eq!(
+        faucet.try_set_budget(&users[0], &token.address, &None),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_pause() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &5000);
+    token2_admin.mint(&faucet_admin, &5000);
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token1.address, 50, 0, FundingSource::Pool),
+    );
+    faucet.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+
+    let user = Address::generate(&e);
+    assert!(faucet.can_claim(&user, &token1.address));
+    assert_eq!(faucet.try_pause(&user), Err(Ok(FaucetError::Unauthorized)));
+    faucet.pause(&faucet_admin);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (symbol_short!("paused"), faucet_admin.clone()).into_val(&e),
+                ().into_val(&e)
+            ),
+        ]
+    );
+    assert!(faucet.is_paused());
+    assert!(!faucet.can_claim(&user, &token1.address));
+    assert_eq!(
+        faucet.try_claim(&user, &token1.address),
+        Err(Ok(FaucetError::Paused))
+    );
+    assert_eq!(
+        faucet.try_deposit_liquidity(&faucet_admin, &100, &100, &100, &100),
+        Err(Ok(FaucetError::Paused))
+    );
+
+    faucet.unpause(&faucet_admin);
+    assert!(!faucet.is_paused());
+    assert!(faucet.can_claim(&user, &token1.address));
+    faucet.claim(&user, &token1.address);
+    assert_eq!(token1.balance(&user), 50);
+
+    // A paused pool still lets the faucet withdraw for proportional claims
+    liqpool.pause(&liqpool.get_admin());
+    assert_eq!(
+        faucet.try_deposit_liquidity(&faucet_admin, &100, &100, &100, &100),
+        Err(Ok(FaucetError::PoolPaused))
+    );
+    faucet.claim(&user, &token1.address);
+    assert_eq!(token1.balance(&user), 100);
+}
+
+#[test]
+fn test_roles_and_admin_transfer() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let owner = Address::generate(&e);
+    let operator = Address::generate(&e);
+    let funder = Address::generate(&e);
+    token1_admin.mint(&funder, &2000);
+    token2_admin.mint(&funder, &2000);
+    let faucet = create_faucet_contract(&e, &owner, &liqpool.address);
+    let slice = asset(&token1.address, 50, 3600, FundingSource::Pool);
+
+    // The owner holds every role
+    assert!(faucet.has_role(&Role::Operator, &owner));
+    assert!(faucet.has_role(&Role::Funder, &owner));
+    assert_eq!(
+        faucet.try_set_asset(&operator, &slice),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+
+    faucet.grant_role(&owner, &Role::Operator, &operator);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (symbol_short!("role"), Role::Operator, operator.clone()).into_val(&e),
+                true.into_val(&e)
+            ),
+        ]
+    );
+    faucet.grant_role(&owner, &Role::Funder, &funder);
+    assert!(faucet.has_role(&Role::Operator, &operator));
+    assert!(!faucet.has_role(&Role::Funder, &operator));
+
+    // Roles only open their own entry points
+    faucet.set_asset(&operator, &slice);
+    assert_eq!(
+        faucet.try_deposit_liquidity(&operator, &1000, &1000, &1000, &1000),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    faucet.deposit_liquidity(&funder, &2000, &2000, &2000, &2000);
+    assert_eq!(
+        faucet.try_set_asset(&funder, &slice),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.try_grant_role(&operator, &Role::Funder, &operator),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+
+    faucet.revoke_role(&owner, &Role::Operator, &operator);
+    assert_eq!(
+        faucet.try_pause(&operator),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+
+    // Ownership moves only once the new owner accepts it
+    let new_owner = Address::generate(&e);
+    assert_eq!(
+        faucet.try_accept_admin(&new_owner),
+        Err(Ok(FaucetError::NoPendingAdmin))
+    );
+    assert_eq!(
+        faucet.try_propose_admin(&operator, &Some(operator.clone())),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    faucet.propose_admin(&owner, &Some(new_owner.clone()));
+    assert_eq!(faucet.get_pending_admin(), Some(new_owner.clone()));
+    assert_eq!(faucet.get_admin(), owner);
+    assert_eq!(
+        faucet.try_accept_admin(&operator),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+
+    faucet.accept_admin(&new_owner);
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (
+                    Symbol::new(&e, "config_changed"),
+                    Symbol::new(&e, "admin"),
+                    new_owner.clone()
+                )
+                    .into_val(&e),
+                (owner.clone(), new_owner.clone()).into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(faucet.get_admin(), new_owner);
+    assert_eq!(faucet.get_pending_admin(), None);
+    assert_eq!(faucet.try_pause(&owner), Err(Ok(FaucetError::Unauthorized)));
+    faucet.pause(&new_owner);
+}
+
+#[test]
+fn test_reclaim_liquidity_and_sweep() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &2000);
+    token2_admin.mint(&faucet_admin, &2000);
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token1.address, 50, 3600, FundingSource::Pool),
+    );
+    faucet.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+
+    // The claim leaves 50 token2 idle in the faucet
+    faucet.claim(&Address::generate(&e), &token1.address);
+    assert_eq!(faucet.get_idle_balances(), (0, 50));
+
+    let treasury = Address::generate(&e);
+    assert_eq!(
+        faucet.try_reclaim_liquidity(&treasury, &100, &0, &0, &treasury),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.try_reclaim_liquidity(&faucet_admin, &0, &0, &0, &treasury),
+        Err(Ok(FaucetError::InvalidShareAmount))
+    );
+    assert_eq!(
+        faucet.try_reclaim_liquidity(&faucet_admin, &100, &101, &0, &treasury),
+        Err(Ok(FaucetError::PoolSlippage))
+    );
+
+    assert_eq!(
+        faucet.reclaim_liquidity(&faucet_admin, &950, &950, &950, &treasury),
+        (950, 950)
+    );
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (symbol_short!("reclaim"), treasury.clone()).into_val(&e),
+                (950_i128, 950_i128, 950_i128).into_val(&e)
+            ),
+        ]
+    );
     assert_eq!(faucet.get_share_balance(), 0);
-    assert_eq!(faucet.get_available_for_claims(), 0);
-}
\ No newline at end of file
+    assert_eq!(token1.balance(&treasury), 950);
+    assert_eq!(token2.balance(&treasury), 950);
+
+    assert_eq!(
+        faucet.try_sweep_token(&treasury, &token2.address, &treasury),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+    assert_eq!(
+        faucet.sweep_token(&faucet_admin, &token2.address, &treasury),
+        50
+    );
+    assert_eq!(
+        contract_events(&e, &faucet.address),
+        vec![
+            &e,
+            (
+                faucet.address.clone(),
+                (
+                    symbol_short!("sweep"),
+                    token2.address.clone(),
+                    treasury.clone()
+                )
+                    .into_val(&e),
+                50_i128.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(faucet.get_idle_balances(), (0, 0));
+    assert_eq!(token2.balance(&treasury), 1000);
+    assert_eq!(
+        faucet.sweep_token(&faucet_admin, &token2.address, &treasury),
+        0
+    );
+}
+
+#[test]
+fn test_upgrade_keeps_state() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &2000);
+    token2_admin.mint(&faucet_admin, &2000);
+    let address = e.register(faucet_v1::WASM, (&faucet_admin, &liqpool.address));
+    let old = faucet_v1::Client::new(&e, &address);
+    let paid_asset = faucet_v1::Asset {
+        token: token2.address.clone(),
+        amount: 50,
+        interval: 3600,
+        source: faucet_v1::FundingSource::Pool,
+    };
+    old.set_asset(&faucet_admin, &paid_asset);
+    old.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+    let user = Address::generate(&e);
+    old.claim(&user, &token2.address);
+    assert_eq!(token2.balance(&user), 50);
+    let shares = old.get_share_balance();
+    assert_eq!(old.get_version(), 1);
+
+    let new_wasm_hash = e.deployer().upload_contract_wasm(faucet_wasm::WASM);
+    assert_eq!(
+        old.try_upgrade(&user, &new_wasm_hash),
+        Err(Ok(faucet_v1::FaucetError::Unauthorized))
+    );
+    old.upgrade(&faucet_admin, &new_wasm_hash);
+    assert_eq!(
+        contract_events(&e, &address),
+        vec![
+            &e,
+            (
+                address.clone(),
+                (symbol_short!("upgrade"), faucet_admin.clone()).into_val(&e),
+                new_wasm_hash.into_val(&e)
+            ),
+        ]
+    );
+
+    let upgraded = faucet_wasm::Client::new(&e, &address);
+    assert_eq!(upgraded.get_version(), 1);
+    assert_eq!(upgraded.get_admin(), faucet_admin);
+    assert_eq!(upgraded.get_share_balance(), shares);
+    assert_eq!(
+        upgraded.list_assets(),
+        vec![
+            &e,
+            faucet_wasm::Asset {
+                token: token2.address.clone(),
+                amount: 50,
+                interval: 3600,
+                source: faucet_wasm::FundingSource::Pool,
+            }
+        ]
+    );
+
+    // Unlike the old `upgrade`, the new one keeps the instance live
+    let config = upgraded.get_ttl_config();
+    e.as_contract(&address, || {
+        assert!(e.storage().instance().get_ttl() < config.instance_threshold)
+    });
+    upgraded.upgrade(&faucet_admin, &new_wasm_hash);
+    e.as_contract(&address, || {
+        assert_eq!(e.storage().instance().get_ttl(), config.instance_extend_to)
+    });
+
+    // Cooldowns written by the old wasm still apply
+    assert_eq!(upgraded.time_until_next_claim(&user, &token2.address), 3600);
+    assert_eq!(
+        upgraded.try_claim(&user, &token2.address),
+        Err(Ok(faucet_wasm::FaucetError::ClaimIntervalNotMet))
+    );
+    e.ledger().with_mut(|li| li.timestamp += 3600);
+    upgraded.claim(&user, &token2.address);
+    assert_eq!(token2.balance(&user), 100);
+    assert_eq!(upgraded.get_claim_count(&user), 2);
+}
+
+#[test]
+fn test_storage_ttl_is_extended() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &2000);
+    token2_admin.mint(&faucet_admin, &2000);
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token2.address, 50, 3600, FundingSource::Pool),
+    );
+    faucet.deposit_liquidity(&faucet_admin, &2000, &2000, &2000, &2000);
+
+    let user = Address::generate(&e);
+    faucet.claim(&user, &token2.address);
+
+    let config = faucet.get_ttl_config();
+    let ttls = || {
+        e.as_contract(&faucet.address, || {
+            let persistent = e.storage().persistent();
+            (
+                e.storage().instance().get_ttl(),
+                persistent.get_ttl(&DataKey::LastClaim(user.clone(), token2.address.clone())),
+                persistent.get_ttl(&DataKey::ClaimCount(user.clone())),
+            )
+        })
+    };
+    let extended = (
+        config.instance_extend_to,
+        config.persistent_extend_to,
+        config.persistent_extend_to,
+    );
+    assert_eq!(ttls(), extended);
+
+    // Bumping every few days keeps the faucet, its pool position and the
+    // user's claim history live well past their first TTL
+    for _ in 0..6 {
+        e.ledger().with_mut(|li| {
+            li.sequence_number += 6 * 17_280;
+            li.timestamp += 6 * 86_400;
+        });
+        faucet.bump(&user);
+        assert_eq!(ttls(), extended);
+    }
+    assert!(e.ledger().sequence() > config.persistent_extend_to);
+    assert_eq!(faucet.get_claim_count(&user), 1);
+    assert_eq!(faucet.get_share_balance(), 950);
+    assert_eq!(faucet.time_until_next_claim(&user, &token2.address), 0);
+
+    let invalid = TtlConfig {
+        instance_threshold: config.instance_extend_to + 1,
+        ..config.clone()
+    };
+    assert_eq!(
+        faucet.try_set_ttl_config(&faucet_admin, &invalid),
+        Err(Ok(FaucetError::InvalidTtl))
+    );
+    let invalid = TtlConfig {
+        persistent_extend_to: e.storage().max_ttl() + 1,
+        ..config.clone()
+    };
+    assert_eq!(
+        faucet.try_set_ttl_config(&faucet_admin, &invalid),
+        Err(Ok(FaucetError::InvalidTtl))
+    );
+    assert_eq!(
+        faucet.try_set_ttl_config(&user, &config),
+        Err(Ok(FaucetError::Unauthorized))
+    );
+
+    let longer = TtlConfig {
+        instance_threshold: 13 * 17_280,
+        instance_extend_to: 14 * 17_280,
+        ..config.clone()
+    };
+    faucet.set_ttl_config(&faucet_admin, &longer);
+    assert_eq!(faucet.get_ttl_config(), longer);
+    assert_eq!(ttls().0, longer.instance_extend_to);
+
+    // A role in use is kept live, so it is not lost to archival
+    let operator = Address::generate(&e);
+    faucet.grant_role(&faucet_admin, &Role::Operator, &operator);
+    let role_ttl = || {
+        e.as_contract(&faucet.address, || {
+            e.storage()
+                .persistent()
+                .get_ttl(&DataKey::Role(Role::Operator, operator.clone()))
+        })
+    };
+    for _ in 0..4 {
+        e.ledger().with_mut(|li| li.sequence_number += 6 * 17_280);
+        faucet.bump(&faucet_admin);
+    }
+    assert!(role_ttl() < config.persistent_threshold);
+    faucet.pause(&operator);
+    assert_eq!(role_ttl(), config.persistent_extend_to);
+}
+
+#[test]
+fn test_claims_with_large_reserves() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    // Reserves of about 1.7e31, as 18-decimal tokens with large supplies reach
+    let big = i128::MAX / 10_i128.pow(7);
+    let amount = big / 1_000;
+    let faucet_admin = Address::generate(&e);
+    token1_admin.mint(&faucet_admin, &big);
+    token2_admin.mint(&faucet_admin, &big);
+    let faucet = create_faucet_contract(&e, &faucet_admin, &liqpool.address);
+    faucet.set_asset(
+        &faucet_admin,
+        &asset(&token2.address, amount, 0, FundingSource::Pool),
+    );
+    faucet.deposit_liquidity(&faucet_admin, &big, &big, &(big / 3), &(big / 3));
+    // All of it but the part backing the pool's locked minimum liquidity
+    assert!(faucet.get_available_for_claims(&token2.address) >= big / 3 - big / 10_i128.pow(20));
+
+    let user1 = Address::generate(&e);
+    faucet.claim(&user1, &token2.address);
+    assert_eq!(token2.balance(&user1), amount);
+
+    faucet.set_claim_mode(&faucet_admin, &ClaimMode::SwapBack);
+    let user2 = Address::generate(&e);
+    faucet.claim(&user2, &token2.address);
+    assert_eq!(token2.balance(&user2), amount);
+}
@@ -0,0 +1,111 @@
+//! Keeps the faucet's storage from being archived. Every state-changing call
+//! extends the instance. Claim history, roles and sponsorships are extended
+//! when a call touches them, and `bump` extends those of a user who has been
+//! away.
+
+use soroban_sdk::{contractimpl, contracttype, Address, Env, IntoVal, Val};
+
+use crate::access::{require_owner, Role};
+use crate::{get_assets, liquidity_pool, DataKey, Faucet, FaucetArgs, FaucetClient, FaucetError};
+
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Ledger counts used to extend the faucet's storage. Once an entry has
+/// fewer than `*_threshold` ledgers left, it is pushed out to `*_extend_to`
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct TtlConfig {
+    pub instance_threshold: u32,
+    pub instance_extend_to: u32,
+    /// Applies to claim history, sponsorships and roles
+    pub persistent_threshold: u32,
+    pub persistent_extend_to: u32,
+}
+
+/// A week for the instance, which every call touches, and a month for user
+/// entries, which may sit untouched between claims
+fn default_config() -> TtlConfig {
+    TtlConfig {
+        instance_threshold: 6 * DAY_IN_LEDGERS,
+        instance_extend_to: 7 * DAY_IN_LEDGERS,
+        persistent_threshold: 29 * DAY_IN_LEDGERS,
+        persistent_extend_to: 30 * DAY_IN_LEDGERS,
+    }
+}
+
+fn get_config(e: &Env) -> TtlConfig {
+    e.storage()
+        .instance()
+        .get(&DataKey::TtlConfig)
+        .unwrap_or_else(default_config)
+}
+
+pub(crate) fn extend_instance(e: &Env) {
+    let config = get_config(e);
+    e.storage()
+        .instance()
+        .extend_ttl(config.instance_threshold, config.instance_extend_to);
+}
+
+/// Extends `key`, which must exist
+pub(crate) fn extend_persistent<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    let config = get_config(e);
+    e.storage().persistent().extend_ttl(
+        key,
+        config.persistent_threshold,
+        config.persistent_extend_to,
+    );
+}
+
+fn extend_if_present<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    if e.storage().persistent().has(key) {
+        extend_persistent(e, key);
+    }
+}
+
+#[contractimpl]
+impl Faucet {
+    /// Owner function to tune storage extension. Fails with `InvalidTtl`
+    /// unless each threshold is at most its `extend_to` and both `extend_to`
+    /// values fit within the network's maximum TTL
+    pub fn set_ttl_config(e: Env, admin: Address, config: TtlConfig) -> Result<(), FaucetError> {
+        require_owner(&e, &admin)?;
+
+        let max_ttl = e.storage().max_ttl();
+        if config.instance_threshold > config.instance_extend_to
+            || config.persistent_threshold > config.persistent_extend_to
+            || config.instance_extend_to > max_ttl
+            || config.persistent_extend_to > max_ttl
+        {
+            return Err(FaucetError::InvalidTtl);
+        }
+
+        e.storage().instance().set(&DataKey::TtlConfig, &config);
+        extend_instance(&e);
+        Ok(())
+    }
+
+    /// Get the ledger counts used to extend the faucet's storage
+    pub fn get_ttl_config(e: Env) -> TtlConfig {
+        get_config(&e)
+    }
+
+    /// Extend the faucet instance, its share balance in the pool and every
+    /// entry kept for `user`. Anyone can pay for it
+    pub fn bump(e: Env, user: Address) {
+        extend_instance(&e);
+
+        let pool_address: Address = e.storage().instance().get(&DataKey::LiquidityPool).unwrap();
+        liquidity_pool::LiquidityPoolClient::new(&e, &pool_address)
+            .bump(&e.current_contract_address());
+
+        for token in get_assets(&e).keys() {
+            extend_if_present(&e, &DataKey::LastClaim(user.clone(), token));
+        }
+        extend_if_present(&e, &DataKey::ClaimCount(user.clone()));
+        extend_if_present(&e, &DataKey::Allowlisted(user.clone()));
+        extend_if_present(&e, &DataKey::SponsoredShares(user.clone()));
+        extend_if_present(&e, &DataKey::Role(Role::Operator, user.clone()));
+        extend_if_present(&e, &DataKey::Role(Role::Funder, user));
+    }
+}
@@ -0,0 +1,33 @@
+//! Wasm upgrades. Version 1 is the first layout written by a faucet with
+//! `upgrade`, faucets deployed before it cannot replace their code and have to
+//! be redeployed.
+
+use soroban_sdk::{contractimpl, Address, BytesN, Env};
+
+use crate::access::require_owner;
+use crate::{events, ttl, DataKey, Faucet, FaucetArgs, FaucetClient, FaucetError};
+
+/// Version of the storage layout written by this wasm. Every change so far
+/// only added keys that default when missing, so upgraded faucets need no
+/// migration. Bump it and add a `migrate` entry point converting the old
+/// layout whenever `DataKey` changes incompatibly
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+#[contractimpl]
+impl Faucet {
+    /// Owner function to replace the faucet wasm. Storage is kept
+    pub fn upgrade(e: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), FaucetError> {
+        require_owner(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        e.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+        events::upgraded(&e, admin, new_wasm_hash);
+        Ok(())
+    }
+
+    /// Get the storage schema version
+    pub fn get_version(e: Env) -> u32 {
+        e.storage().instance().get(&DataKey::Version).unwrap()
+    }
+}
@@ -0,0 +1,98 @@
+use soroban_sdk::{contracttype, symbol_short, Address, BytesN, Env};
+
+/// Data of the `("deposit", to)` event
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DepositEvent {
+    pub amount_a: i128,
+    pub amount_b: i128,
+    pub shares_minted: i128,
+}
+
+/// Data of the `("swap", to, buy_a)` event
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct SwapEvent {
+    pub amount_in: i128,
+    pub amount_out: i128,
+}
+
+/// Data of the `("withdraw", to)` event
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct WithdrawEvent {
+    pub amount_a: i128,
+    pub amount_b: i128,
+    pub shares_burned: i128,
+}
+
+pub(crate) fn deposit(e: &Env, to: Address, amount_a: i128, amount_b: i128, shares_minted: i128) {
+    e.events().publish(
+        (symbol_short!("deposit"), to),
+        DepositEvent {
+            amount_a,
+            amount_b,
+            shares_minted,
+        },
+    );
+}
+
+pub(crate) fn swap(e: &Env, to: Address, buy_a: bool, amount_in: i128, amount_out: i128) {
+    e.events().publish(
+        (symbol_short!("swap"), to, buy_a),
+        SwapEvent {
+            amount_in,
+            amount_out,
+        },
+    );
+}
+
+pub(crate) fn withdraw(e: &Env, to: Address, amount_a: i128, amount_b: i128, shares_burned: i128) {
+    e.events().publish(
+        (symbol_short!("withdraw"), to),
+        WithdrawEvent {
+            amount_a,
+            amount_b,
+            shares_burned,
+        },
+    );
+}
+
+/// Published as `("mint", to)` with the LP shares created, so share
+/// balances can be followed from events like any SEP-41 token
+pub(crate) fn shares_minted(e: &Env, to: Address, amount: i128) {
+    e.events().publish((symbol_short!("mint"), to), amount);
+}
+
+/// Published as `("burn", from)` with the LP shares destroyed by `withdraw`
+pub(crate) fn shares_burned(e: &Env, from: Address, amount: i128) {
+    e.events().publish((symbol_short!("burn"), from), amount);
+}
+
+/// Published after every reserve change with the new reserves
+pub(crate) fn sync(e: &Env, reserve_a: i128, reserve_b: i128) {
+    e.events()
+        .publish((symbol_short!("sync"),), (reserve_a, reserve_b));
+}
+
+/// Published as `("skim", to)` with the amounts of token A and B sent
+pub(crate) fn skim(e: &Env, to: Address, amount_a: i128, amount_b: i128) {
+    e.events()
+        .publish((symbol_short!("skim"), to), (amount_a, amount_b));
+}
+
+/// Published as `("paused", caller)` when swaps and deposits are stopped
+pub(crate) fn paused(e: &Env, caller: Address) {
+    e.events().publish((symbol_short!("paused"), caller), ());
+}
+
+/// Published as `("unpaused", caller)` when swaps and deposits resume
+pub(crate) fn unpaused(e: &Env, caller: Address) {
+    e.events().publish((symbol_short!("unpaused"), caller), ());
+}
+
+/// Published as `("upgrade", admin)` with the new wasm hash
+pub(crate) fn upgraded(e: &Env, admin: Address, new_wasm_hash: BytesN<32>) {
+    e.events()
+        .publish((symbol_short!("upgrade"), admin), new_wasm_hash);
+}
@@ -1,9 +1,21 @@
 #![no_std]
 
+mod events;
+mod math;
+mod oracle;
+mod share;
 mod test;
+mod ttl;
 
-use num_integer::Roots;
-use soroban_sdk::{contract, contractimpl, contractmeta, contracttype, token, Address, Env};
+use core::cmp::Ordering;
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contractmeta, contracttype, panic_with_error, token,
+    Address, BytesN, Env,
+};
+
+pub use events::{DepositEvent, SwapEvent, WithdrawEvent};
+pub use oracle::PRICE_SCALE;
+pub use ttl::TtlConfig;
 
 #[derive(Clone)]
 #[contracttype]
@@ -14,6 +26,92 @@ pub enum DataKey {
     ReserveA,
     ReserveB,
     Shares(Address),
+    Admin,
+    FeeBps,
+    ProtocolFeeBps,
+    ProtocolFeeRecipient,
+    ProtocolFeesA,
+    ProtocolFeesB,
+    Allowance(share::AllowanceDataKey),
+    Guardian,
+    Paused,
+    Version,
+    TtlConfig,
+    Oracle,
+    Observations,
+}
+
+/// Basis points denominator used by every fee
+const FEE_DENOMINATOR: i128 = 10_000;
+
+/// Upper bound for the swap fee (10%)
+const MAX_FEE_BPS: u32 = 1_000;
+
+/// Shares minted to the pool itself by the first deposit, where they stay
+/// locked for good. They keep the reserves from being drained to zero and make
+/// inflating the price of a share by donating to the pool cost the donor
+/// about a thousand times what it takes from later depositors
+const MINIMUM_LIQUIDITY: i128 = 1_000;
+
+/// Version of the storage layout written by this wasm. Every change so far
+/// only added keys that default when missing, so upgraded pools need no
+/// migration. Bump it and add a `migrate` entry point converting the old
+/// layout whenever `DataKey` changes incompatibly
+const SCHEMA_VERSION: u32 = 1;
+
+/// Errors returned by the pool. The numeric codes are part of the contract
+/// interface and must stay stable.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum PoolError {
+    /// `token_a` must sort strictly before `token_b`
+    InvalidTokenOrder = 1,
+    /// The caller owns fewer shares than requested
+    InsufficientShares = 2,
+    /// The computed `amount_b` is below `min_b`
+    AmountBBelowMin = 3,
+    /// The computed `amount_a` is above `desired_a` or below `min_a`
+    AmountAInvalid = 4,
+    /// Deposit and swap amounts must be strictly positive
+    NonPositiveAmounts = 5,
+    /// The pool does not hold more of the bought token than `out`
+    InsufficientReserve = 6,
+    /// The required input exceeds `in_max`
+    InMaxExceeded = 7,
+    /// The constant product invariant would decrease
+    InvariantViolated = 8,
+    /// The reserves after a swap or sync must stay strictly positive
+    NonPositiveReserves = 9,
+    /// The withdrawn amounts are below `min_a` or `min_b`
+    MinNotSatisfied = 10,
+    /// The swap output is zero or below `min_out`
+    OutBelowMin = 11,
+    /// The caller is not the pool admin
+    Unauthorized = 12,
+    /// The swap fee is above `MAX_FEE_BPS` or the protocol share above 100%
+    InvalidFee = 13,
+    /// No protocol fee recipient is configured
+    NoFeeRecipient = 14,
+    /// Share token amounts must not be negative
+    NegativeAmount = 15,
+    /// The spender's share allowance is too low
+    InsufficientAllowance = 16,
+    /// A non-zero allowance must not expire before the current ledger
+    InvalidExpiration = 17,
+    /// Swaps, deposits and syncs are paused
+    Paused = 18,
+    /// A TTL threshold is above its extension or the maximum TTL
+    InvalidTtl = 19,
+    /// An amount does not fit in i128
+    Overflow = 20,
+    /// The deposit would mint no shares, or no more than `MINIMUM_LIQUIDITY`
+    /// on the first deposit
+    InsufficientLiquidityMinted = 21,
+    /// The `consult` window must be at least one second
+    InvalidWindow = 22,
+    /// No stored observation is old enough to cover the `consult` window
+    NotEnoughHistory = 23,
 }
 
 fn get_token_a(e: &Env) -> Address {
@@ -40,25 +138,64 @@ fn get_balance(e: &Env, contract: Address) -> i128 {
     token::Client::new(e, &contract).balance(&e.current_contract_address())
 }
 
+fn get_admin(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::Admin).unwrap()
+}
+
+fn get_fee_bps(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::FeeBps).unwrap()
+}
+
+fn get_protocol_fee_bps(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&DataKey::ProtocolFeeBps)
+        .unwrap_or(0)
+}
+
+fn get_protocol_fee_recipient(e: &Env) -> Option<Address> {
+    e.storage().instance().get(&DataKey::ProtocolFeeRecipient)
+}
+
+fn get_protocol_fees_a(e: &Env) -> i128 {
+    e.storage()
+        .instance()
+        .get(&DataKey::ProtocolFeesA)
+        .unwrap_or(0)
+}
+
+fn get_protocol_fees_b(e: &Env) -> i128 {
+    e.storage()
+        .instance()
+        .get(&DataKey::ProtocolFeesB)
+        .unwrap_or(0)
+}
+
+/// Pool-owned balance of token A, excluding accrued protocol fees
 fn get_balance_a(e: &Env) -> i128 {
-    get_balance(e, get_token_a(e))
+    get_balance(e, get_token_a(e)) - get_protocol_fees_a(e)
 }
 
+/// Pool-owned balance of token B, excluding accrued protocol fees
 fn get_balance_b(e: &Env) -> i128 {
-    get_balance(e, get_token_b(e))
+    get_balance(e, get_token_b(e)) - get_protocol_fees_b(e)
 }
 
 fn get_shares(e: &Env, user: &Address) -> i128 {
-    e.storage()
-        .persistent()
-        .get(&DataKey::Shares(user.clone()))
-        .unwrap_or(0)
+    let key = DataKey::Shares(user.clone());
+    match e.storage().persistent().get(&key) {
+        Some(shares) => {
+            ttl::extend_persistent(e, &key);
+            shares
+        }
+        None => 0,
+    }
 }
 
 fn put_shares(e: &Env, user: &Address, amount: i128) {
-    e.storage()
-        .persistent()
-        .set(&DataKey::Shares(user.clone()), &amount);
+    let key = DataKey::Shares(user.clone());
+    e.storage().persistent().set(&key, &amount);
+    ttl::extend_persistent(e, &key);
 }
 
 fn put_token_a(e: &Env, contract: Address) {
@@ -69,6 +206,75 @@ fn put_token_b(e: &Env, contract: Address) {
     e.storage().instance().set(&DataKey::TokenB, &contract);
 }
 
+fn put_admin(e: &Env, admin: &Address) {
+    e.storage().instance().set(&DataKey::Admin, admin);
+}
+
+fn put_fee_bps(e: &Env, fee_bps: u32) {
+    e.storage().instance().set(&DataKey::FeeBps, &fee_bps);
+}
+
+fn put_protocol_fee(e: &Env, recipient: &Address, share_bps: u32) {
+    e.storage()
+        .instance()
+        .set(&DataKey::ProtocolFeeRecipient, recipient);
+    e.storage()
+        .instance()
+        .set(&DataKey::ProtocolFeeBps, &share_bps);
+}
+
+fn put_protocol_fees_a(e: &Env, amount: i128) {
+    e.storage().instance().set(&DataKey::ProtocolFeesA, &amount)
+}
+
+fn put_protocol_fees_b(e: &Env, amount: i128) {
+    e.storage().instance().set(&DataKey::ProtocolFeesB, &amount)
+}
+
+fn check_admin(e: &Env, admin: &Address) -> Result<(), PoolError> {
+    admin.require_auth();
+    if *admin != get_admin(e) {
+        return Err(PoolError::Unauthorized);
+    }
+    Ok(())
+}
+
+fn get_guardian(e: &Env) -> Option<Address> {
+    e.storage().instance().get(&DataKey::Guardian)
+}
+
+fn is_paused(e: &Env) -> bool {
+    e.storage()
+        .instance()
+        .get(&DataKey::Paused)
+        .unwrap_or(false)
+}
+
+/// The admin and the guardian can both pause and unpause the pool
+fn check_pauser(e: &Env, caller: &Address) -> Result<(), PoolError> {
+    caller.require_auth();
+    if *caller != get_admin(e) && Some(caller.clone()) != get_guardian(e) {
+        return Err(PoolError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Pools deployed before the layout was versioned count as version 0
+fn get_version(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::Version).unwrap_or(0)
+}
+
+fn put_version(e: &Env, version: u32) {
+    e.storage().instance().set(&DataKey::Version, &version);
+}
+
+fn check_not_paused(e: &Env) -> Result<(), PoolError> {
+    if is_paused(e) {
+        return Err(PoolError::Paused);
+    }
+    Ok(())
+}
+
 fn put_total_shares(e: &Env, amount: i128) {
     e.storage().instance().set(&DataKey::TotalShares, &amount)
 }
@@ -81,14 +287,23 @@ fn put_reserve_b(e: &Env, amount: i128) {
     e.storage().instance().set(&DataKey::ReserveB, &amount)
 }
 
-fn burn_shares(e: &Env, from: &Address, amount: i128) {
+/// Sets both reserves once the oracle has accumulated the old ones
+fn put_reserves(e: &Env, reserve_a: i128, reserve_b: i128) {
+    oracle::update(e);
+    put_reserve_a(e, reserve_a);
+    put_reserve_b(e, reserve_b);
+}
+
+fn burn_shares(e: &Env, from: &Address, amount: i128) -> Result<(), PoolError> {
     let current_shares = get_shares(e, from);
     if current_shares < amount {
-        panic!("insufficient shares");
+        return Err(PoolError::InsufficientShares);
     }
     let total = get_total_shares(e);
     put_shares(e, from, current_shares - amount);
     put_total_shares(e, total - amount);
+    events::shares_burned(e, from.clone(), amount);
+    Ok(())
 }
 
 fn mint_shares(e: &Env, to: &Address, amount: i128) {
@@ -96,6 +311,7 @@ fn mint_shares(e: &Env, to: &Address, amount: i128) {
     let total = get_total_shares(e);
     put_shares(e, to, current_shares + amount);
     put_total_shares(e, total + amount);
+    events::shares_minted(e, to.clone(), amount);
 }
 
 fn transfer(e: &Env, token: Address, to: Address, amount: i128) {
@@ -117,30 +333,226 @@ fn get_deposit_amounts(
     min_b: i128,
     reserve_a: i128,
     reserve_b: i128,
-) -> (i128, i128) {
+) -> Result<(i128, i128), PoolError> {
     if reserve_a == 0 && reserve_b == 0 {
-        return (desired_a, desired_b);
+        return Ok((desired_a, desired_b));
     }
 
-    let amount_b = desired_a * reserve_b / reserve_a;
+    let amount_b = math::mul_div(desired_a, reserve_b, reserve_a).ok_or(PoolError::Overflow)?;
     if amount_b <= desired_b {
         if amount_b < min_b {
-            panic!("amount_b less than min")
+            return Err(PoolError::AmountBBelowMin);
         }
-        (desired_a, amount_b)
+        Ok((desired_a, amount_b))
     } else {
-        let amount_a = desired_b * reserve_a / reserve_b;
+        let amount_a = math::mul_div(desired_b, reserve_a, reserve_b).ok_or(PoolError::Overflow)?;
         if amount_a > desired_a || amount_a < min_a {
-            panic!("amount_a invalid")
+            return Err(PoolError::AmountAInvalid);
         }
-        (amount_a, desired_b)
+        Ok((amount_a, desired_b))
+    }
+}
+
+/// Input needed to buy `out` from a pool holding `reserve_buy`, fee included
+fn get_amount_in(
+    reserve_sell: i128,
+    reserve_buy: i128,
+    out: i128,
+    fee_bps: u32,
+) -> Result<i128, PoolError> {
+    if out <= 0 {
+        return Err(PoolError::NonPositiveAmounts);
+    }
+    if reserve_buy <= out {
+        return Err(PoolError::InsufficientReserve);
+    }
+
+    let n = reserve_sell
+        .checked_mul(FEE_DENOMINATOR)
+        .ok_or(PoolError::Overflow)?;
+    let d = (reserve_buy - out)
+        .checked_mul(FEE_DENOMINATOR - fee_bps as i128)
+        .ok_or(PoolError::Overflow)?;
+    math::mul_div(n, out, d)
+        .and_then(|amount_in| amount_in.checked_add(1))
+        .ok_or(PoolError::Overflow)
+}
+
+/// Output received for selling `amount_in`, fee included
+fn get_amount_out(
+    reserve_sell: i128,
+    reserve_buy: i128,
+    amount_in: i128,
+    fee_bps: u32,
+) -> Result<i128, PoolError> {
+    if amount_in <= 0 {
+        return Err(PoolError::NonPositiveAmounts);
+    }
+    if reserve_sell <= 0 || reserve_buy <= 0 {
+        return Err(PoolError::InsufficientReserve);
+    }
+
+    let amount_in_after_fee = amount_in
+        .checked_mul(FEE_DENOMINATOR - fee_bps as i128)
+        .ok_or(PoolError::Overflow)?;
+    let d = reserve_sell
+        .checked_mul(FEE_DENOMINATOR)
+        .and_then(|n| n.checked_add(amount_in_after_fee))
+        .ok_or(PoolError::Overflow)?;
+    math::mul_div(amount_in_after_fee, reserve_buy, d).ok_or(PoolError::Overflow)
+}
+
+/// Shares minted to the depositor of `amount_a` and `amount_b`. Only the
+/// deposited amounts count, not the pool balances, so tokens sent to the pool
+/// outside of `deposit` do not change what a deposit receives
+fn get_shares_minted(
+    amount_a: i128,
+    amount_b: i128,
+    reserve_a: i128,
+    reserve_b: i128,
+    total_shares: i128,
+) -> Result<i128, PoolError> {
+    let shares = if total_shares == 0 {
+        math::sqrt_product(amount_a, amount_b) - MINIMUM_LIQUIDITY
+    } else {
+        let shares_a = math::mul_div(amount_a, total_shares, reserve_a);
+        let shares_b = math::mul_div(amount_b, total_shares, reserve_b);
+        shares_a
+            .zip(shares_b)
+            .map(|(shares_a, shares_b)| shares_a.min(shares_b))
+            .ok_or(PoolError::Overflow)?
+    };
+
+    if shares <= 0 {
+        return Err(PoolError::InsufficientLiquidityMinted);
+    }
+    Ok(shares)
+}
+
+/// Amounts paid out for burning `share_amount` out of `total_shares`
+fn get_withdraw_amounts(
+    balance_a: i128,
+    balance_b: i128,
+    share_amount: i128,
+    total_shares: i128,
+) -> Result<(i128, i128), PoolError> {
+    if share_amount <= 0 {
+        return Err(PoolError::NonPositiveAmounts);
+    }
+    if share_amount > total_shares || total_shares == 0 {
+        return Err(PoolError::InsufficientShares);
+    }
+
+    let out_a = math::mul_div(balance_a, share_amount, total_shares);
+    let out_b = math::mul_div(balance_b, share_amount, total_shares);
+    out_a.zip(out_b).ok_or(PoolError::Overflow)
+}
+
+/// Reserves ordered as `(reserve_sell, reserve_buy)`
+fn get_swap_reserves(buy_a: bool, reserve_a: i128, reserve_b: i128) -> (i128, i128) {
+    if buy_a {
+        (reserve_b, reserve_a)
+    } else {
+        (reserve_a, reserve_b)
+    }
+}
+
+/// Part of the swap fee on `sell_amount` that is owed to the protocol
+fn get_protocol_fee(sell_amount: i128, fee_bps: u32, protocol_fee_bps: u32) -> i128 {
+    // Cannot fail: the fee is a fraction of `sell_amount`
+    math::mul_div(
+        sell_amount,
+        fee_bps as i128 * protocol_fee_bps as i128,
+        FEE_DENOMINATOR * FEE_DENOMINATOR,
+    )
+    .unwrap()
+}
+
+/// Pulls `sell_amount` from `to`, checks the fee-adjusted constant product
+/// invariant, pays out `out` of the bought token and stores the new reserves.
+/// The protocol's cut of the fee is set aside and left out of the reserves.
+fn execute_swap(
+    e: &Env,
+    to: Address,
+    buy_a: bool,
+    sell_amount: i128,
+    out: i128,
+    reserve_a: i128,
+    reserve_b: i128,
+) -> Result<(), PoolError> {
+    let sell_token = if buy_a {
+        get_token_b(e)
+    } else {
+        get_token_a(e)
+    };
+    let sell_token_client = token::Client::new(e, &sell_token);
+    sell_token_client.transfer(&to, &e.current_contract_address(), &sell_amount);
+
+    let (balance_a, balance_b) = (get_balance_a(e), get_balance_b(e));
+    let fee_bps = get_fee_bps(e);
+    let residue_numerator = FEE_DENOMINATOR - fee_bps as i128;
+    let residue_denominator = FEE_DENOMINATOR;
+    let zero = 0;
+
+    let new_invariant_factor = |balance: i128, reserve: i128, out: i128| {
+        let delta = balance - reserve - out;
+        let adj_delta = if delta > zero {
+            residue_numerator.checked_mul(delta)
+        } else {
+            residue_denominator.checked_mul(delta)
+        };
+        residue_denominator
+            .checked_mul(reserve)?
+            .checked_add(adj_delta?)
+    };
+
+    let (out_a, out_b) = if buy_a { (out, 0) } else { (0, out) };
+    let new_inv_a = new_invariant_factor(balance_a, reserve_a, out_a).ok_or(PoolError::Overflow)?;
+    let new_inv_b = new_invariant_factor(balance_b, reserve_b, out_b).ok_or(PoolError::Overflow)?;
+    let old_inv_a = residue_denominator
+        .checked_mul(reserve_a)
+        .ok_or(PoolError::Overflow)?;
+    let old_inv_b = residue_denominator
+        .checked_mul(reserve_b)
+        .ok_or(PoolError::Overflow)?;
+
+    if math::cmp_products(new_inv_a, new_inv_b, old_inv_a, old_inv_b) == Ordering::Less {
+        return Err(PoolError::InvariantViolated);
+    }
+
+    if buy_a {
+        transfer_a(e, to.clone(), out_a);
+    } else {
+        transfer_b(e, to.clone(), out_b);
     }
+
+    let protocol_fee = get_protocol_fee(sell_amount, fee_bps, get_protocol_fee_bps(e));
+    let (fee_a, fee_b) = if buy_a {
+        (0, protocol_fee)
+    } else {
+        (protocol_fee, 0)
+    };
+    put_protocol_fees_a(e, get_protocol_fees_a(e) + fee_a);
+    put_protocol_fees_b(e, get_protocol_fees_b(e) + fee_b);
+
+    let new_reserve_a = balance_a - out_a - fee_a;
+    let new_reserve_b = balance_b - out_b - fee_b;
+
+    if new_reserve_a <= 0 || new_reserve_b <= 0 {
+        return Err(PoolError::NonPositiveReserves);
+    }
+
+    put_reserves(e, new_reserve_a, new_reserve_b);
+
+    events::swap(e, to, buy_a, sell_amount, out);
+    events::sync(e, new_reserve_a, new_reserve_b);
+    Ok(())
 }
 
 // Metadata
 contractmeta!(
     key = "Description",
-    val = "Constant product AMM with a .3% swap fee"
+    val = "Constant product AMM with a configurable swap fee"
 );
 
 #[contract]
@@ -148,17 +560,25 @@ pub struct LiquidityPool;
 
 #[contractimpl]
 impl LiquidityPool {
-    /// Initialize the liquidity pool with two tokens
-    pub fn __constructor(e: Env, token_a: Address, token_b: Address) {
+    /// Initialize the liquidity pool with two tokens and a swap fee in basis
+    /// points (30 = 0.3%)
+    pub fn __constructor(e: Env, token_a: Address, token_b: Address, admin: Address, fee_bps: u32) {
         if token_a >= token_b {
-            panic!("token_a must be less than token_b");
+            panic_with_error!(&e, PoolError::InvalidTokenOrder);
+        }
+        if fee_bps > MAX_FEE_BPS {
+            panic_with_error!(&e, PoolError::InvalidFee);
         }
 
+        put_admin(&e, &admin);
+        put_fee_bps(&e, fee_bps);
         put_token_a(&e, token_a);
         put_token_b(&e, token_b);
         put_total_shares(&e, 0);
         put_reserve_a(&e, 0);
         put_reserve_b(&e, 0);
+        put_version(&e, SCHEMA_VERSION);
+        ttl::extend_instance(&e);
     }
 
     /// Get user's share balance
@@ -174,144 +594,350 @@ impl LiquidityPool {
         min_a: i128,
         desired_b: i128,
         min_b: i128,
-    ) {
+    ) -> Result<(), PoolError> {
         to.require_auth();
+        ttl::extend_instance(&e);
+        check_not_paused(&e)?;
 
         let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
         let (amount_a, amount_b) =
-            get_deposit_amounts(desired_a, min_a, desired_b, min_b, reserve_a, reserve_b);
+            get_deposit_amounts(desired_a, min_a, desired_b, min_b, reserve_a, reserve_b)?;
 
         if amount_a <= 0 || amount_b <= 0 {
-            panic!("both amounts must be strictly positive");
+            return Err(PoolError::NonPositiveAmounts);
         }
 
+        let total_shares = get_total_shares(&e);
+        let shares_minted =
+            get_shares_minted(amount_a, amount_b, reserve_a, reserve_b, total_shares)?;
+
         let token_a_client = token::Client::new(&e, &get_token_a(&e));
         let token_b_client = token::Client::new(&e, &get_token_b(&e));
 
         token_a_client.transfer(&to, &e.current_contract_address(), &amount_a);
         token_b_client.transfer(&to, &e.current_contract_address(), &amount_b);
 
-        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
-        let total_shares = get_total_shares(&e);
+        if total_shares == 0 {
+            // Nobody can move these: the pool never authorizes spending its
+            // own shares
+            mint_shares(&e, &e.current_contract_address(), MINIMUM_LIQUIDITY);
+        }
+        mint_shares(&e, &to, shares_minted);
 
-        let zero = 0;
-        let new_total_shares = if reserve_a > zero && reserve_b > zero {
-            let shares_a = (balance_a * total_shares) / reserve_a;
-            let shares_b = (balance_b * total_shares) / reserve_b;
-            shares_a.min(shares_b)
-        } else {
-            (balance_a * balance_b).isqrt()
-        };
+        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
+        put_reserves(&e, balance_a, balance_b);
 
-        mint_shares(&e, &to, new_total_shares - total_shares);
-        put_reserve_a(&e, balance_a);
-        put_reserve_b(&e, balance_b);
+        events::deposit(&e, to, amount_a, amount_b, shares_minted);
+        events::sync(&e, balance_a, balance_b);
+        Ok(())
     }
 
     /// Swap tokens
-    pub fn swap(e: Env, to: Address, buy_a: bool, out: i128, in_max: i128) {
+    pub fn swap(
+        e: Env,
+        to: Address,
+        buy_a: bool,
+        out: i128,
+        in_max: i128,
+    ) -> Result<(), PoolError> {
         to.require_auth();
+        ttl::extend_instance(&e);
+        check_not_paused(&e)?;
 
         let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
-        let (reserve_sell, reserve_buy) = if buy_a {
-            (reserve_b, reserve_a)
-        } else {
-            (reserve_a, reserve_b)
-        };
+        let (reserve_sell, reserve_buy) = get_swap_reserves(buy_a, reserve_a, reserve_b);
 
-        if reserve_buy < out {
-            panic!("not enough token to buy");
-        }
-
-        let n = reserve_sell * out * 1000;
-        let d = (reserve_buy - out) * 997;
-        let sell_amount = (n / d) + 1;
+        let sell_amount = get_amount_in(reserve_sell, reserve_buy, out, get_fee_bps(&e))?;
         if sell_amount > in_max {
-            panic!("in amount is over max")
+            return Err(PoolError::InMaxExceeded);
         }
 
-        let sell_token = if buy_a {
-            get_token_b(&e)
-        } else {
-            get_token_a(&e)
-        };
-        let sell_token_client = token::Client::new(&e, &sell_token);
-        sell_token_client.transfer(&to, &e.current_contract_address(), &sell_amount);
-
-        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
-        let residue_numerator = 997;
-        let residue_denominator = 1000;
-        let zero = 0;
-
-        let new_invariant_factor = |balance: i128, reserve: i128, out: i128| {
-            let delta = balance - reserve - out;
-            let adj_delta = if delta > zero {
-                residue_numerator * delta
-            } else {
-                residue_denominator * delta
-            };
-            residue_denominator * reserve + adj_delta
-        };
-
-        let (out_a, out_b) = if buy_a { (out, 0) } else { (0, out) };
-        let new_inv_a = new_invariant_factor(balance_a, reserve_a, out_a);
-        let new_inv_b = new_invariant_factor(balance_b, reserve_b, out_b);
-        let old_inv_a = residue_denominator * reserve_a;
-        let old_inv_b = residue_denominator * reserve_b;
-
-        if new_inv_a * new_inv_b < old_inv_a * old_inv_b {
-            panic!("constant product invariant does not hold");
-        }
+        execute_swap(&e, to, buy_a, sell_amount, out, reserve_a, reserve_b)
+    }
 
-        if buy_a {
-            transfer_a(&e, to, out_a);
-        } else {
-            transfer_b(&e, to, out_b);
-        }
+    /// Swap exactly `amount_in` of the sold token for at least `min_out` of
+    /// the other one. Returns the amount received.
+    pub fn swap_exact_in(
+        e: Env,
+        to: Address,
+        sell_a: bool,
+        amount_in: i128,
+        min_out: i128,
+    ) -> Result<i128, PoolError> {
+        to.require_auth();
+        ttl::extend_instance(&e);
+        check_not_paused(&e)?;
 
-        let new_reserve_a = balance_a - out_a;
-        let new_reserve_b = balance_b - out_b;
+        let buy_a = !sell_a;
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let (reserve_sell, reserve_buy) = get_swap_reserves(buy_a, reserve_a, reserve_b);
 
-        if new_reserve_a <= 0 || new_reserve_b <= 0 {
-            panic!("new reserves must be strictly positive");
+        let out = get_amount_out(reserve_sell, reserve_buy, amount_in, get_fee_bps(&e))?;
+        if out <= 0 || out < min_out {
+            return Err(PoolError::OutBelowMin);
         }
 
-        put_reserve_a(&e, new_reserve_a);
-        put_reserve_b(&e, new_reserve_b);
+        execute_swap(&e, to, buy_a, amount_in, out, reserve_a, reserve_b)?;
+        Ok(out)
     }
 
-    /// Withdraw liquidity from the pool
+    /// Withdraw liquidity from the pool. Stays open while the pool is paused
     pub fn withdraw(
         e: Env,
         to: Address,
         share_amount: i128,
         min_a: i128,
         min_b: i128,
-    ) -> (i128, i128) {
+    ) -> Result<(i128, i128), PoolError> {
         to.require_auth();
+        ttl::extend_instance(&e);
 
+        if share_amount <= 0 {
+            return Err(PoolError::NonPositiveAmounts);
+        }
         let current_shares = get_shares(&e, &to);
         if current_shares < share_amount {
-            panic!("insufficient shares");
+            return Err(PoolError::InsufficientShares);
         }
 
         let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
         let total_shares = get_total_shares(&e);
-
-        let out_a = (balance_a * share_amount) / total_shares;
-        let out_b = (balance_b * share_amount) / total_shares;
+        let (out_a, out_b) =
+            get_withdraw_amounts(balance_a, balance_b, share_amount, total_shares)?;
 
         if out_a < min_a || out_b < min_b {
-            panic!("min not satisfied");
+            return Err(PoolError::MinNotSatisfied);
         }
 
-        burn_shares(&e, &to, share_amount);
+        burn_shares(&e, &to, share_amount)?;
         transfer_a(&e, to.clone(), out_a);
-        transfer_b(&e, to, out_b);
-        put_reserve_a(&e, balance_a - out_a);
-        put_reserve_b(&e, balance_b - out_b);
+        transfer_b(&e, to.clone(), out_b);
+        put_reserves(&e, balance_a - out_a, balance_b - out_b);
+
+        events::withdraw(&e, to, out_a, out_b, share_amount);
+        events::sync(&e, balance_a - out_a, balance_b - out_b);
+
+        Ok((out_a, out_b))
+    }
+
+    /// Quote the input needed to buy exactly `out`, as charged by `swap`
+    pub fn quote_in(e: Env, buy_a: bool, out: i128) -> Result<i128, PoolError> {
+        let (reserve_sell, reserve_buy) =
+            get_swap_reserves(buy_a, get_reserve_a(&e), get_reserve_b(&e));
+        get_amount_in(reserve_sell, reserve_buy, out, get_fee_bps(&e))
+    }
+
+    /// Quote the output for selling exactly `amount_in`, as paid by
+    /// `swap_exact_in`
+    pub fn quote_out(e: Env, sell_a: bool, amount_in: i128) -> Result<i128, PoolError> {
+        let (reserve_sell, reserve_buy) =
+            get_swap_reserves(!sell_a, get_reserve_a(&e), get_reserve_b(&e));
+        get_amount_out(reserve_sell, reserve_buy, amount_in, get_fee_bps(&e))
+    }
+
+    /// Quote a deposit. Returns the amounts `deposit` would pull and the
+    /// shares it would mint.
+    pub fn quote_deposit(
+        e: Env,
+        desired_a: i128,
+        desired_b: i128,
+    ) -> Result<(i128, i128, i128), PoolError> {
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let (amount_a, amount_b) =
+            get_deposit_amounts(desired_a, 0, desired_b, 0, reserve_a, reserve_b)?;
+
+        if amount_a <= 0 || amount_b <= 0 {
+            return Err(PoolError::NonPositiveAmounts);
+        }
+
+        let shares_minted = get_shares_minted(
+            amount_a,
+            amount_b,
+            reserve_a,
+            reserve_b,
+            get_total_shares(&e),
+        )?;
+        Ok((amount_a, amount_b, shares_minted))
+    }
+
+    /// Quote the amounts `withdraw` would pay for `share_amount` shares
+    pub fn quote_withdraw(e: Env, share_amount: i128) -> Result<(i128, i128), PoolError> {
+        get_withdraw_amounts(
+            get_balance_a(&e),
+            get_balance_b(&e),
+            share_amount,
+            get_total_shares(&e),
+        )
+    }
+
+    /// Admin function to set the swap fee in basis points
+    pub fn set_fee(e: Env, admin: Address, fee_bps: u32) -> Result<(), PoolError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        if fee_bps > MAX_FEE_BPS {
+            return Err(PoolError::InvalidFee);
+        }
 
-        (out_a, out_b)
+        put_fee_bps(&e, fee_bps);
+        Ok(())
+    }
+
+    /// Admin function to route `share_bps` of every swap fee to `recipient`.
+    /// A share of 0 turns the protocol fee off.
+    pub fn set_protocol_fee(
+        e: Env,
+        admin: Address,
+        recipient: Address,
+        share_bps: u32,
+    ) -> Result<(), PoolError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        if share_bps as i128 > FEE_DENOMINATOR {
+            return Err(PoolError::InvalidFee);
+        }
+
+        put_protocol_fee(&e, &recipient, share_bps);
+        Ok(())
+    }
+
+    /// Admin function to replace the pool wasm. Storage is kept
+    pub fn upgrade(e: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), PoolError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        e.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+        events::upgraded(&e, admin, new_wasm_hash);
+        Ok(())
+    }
+
+    /// Get the storage schema version
+    pub fn get_version(e: Env) -> u32 {
+        get_version(&e)
+    }
+
+    /// Admin function to appoint the guardian, who can pause the pool
+    /// alongside the admin. `None` removes the guardian
+    pub fn set_guardian(
+        e: Env,
+        admin: Address,
+        guardian: Option<Address>,
+    ) -> Result<(), PoolError> {
+        check_admin(&e, &admin)?;
+        ttl::extend_instance(&e);
+
+        match guardian {
+            Some(guardian) => e.storage().instance().set(&DataKey::Guardian, &guardian),
+            None => e.storage().instance().remove(&DataKey::Guardian),
+        }
+        Ok(())
+    }
+
+    /// Stop swaps, deposits and syncs. Withdrawals stay open
+    pub fn pause(e: Env, caller: Address) -> Result<(), PoolError> {
+        check_pauser(&e, &caller)?;
+        ttl::extend_instance(&e);
+
+        e.storage().instance().set(&DataKey::Paused, &true);
+        events::paused(&e, caller);
+        Ok(())
+    }
+
+    /// Resume swaps and deposits
+    pub fn unpause(e: Env, caller: Address) -> Result<(), PoolError> {
+        check_pauser(&e, &caller)?;
+        ttl::extend_instance(&e);
+
+        e.storage().instance().set(&DataKey::Paused, &false);
+        events::unpaused(&e, caller);
+        Ok(())
+    }
+
+    /// Send the accrued protocol fees to the recipient. Returns the amounts
+    /// sent.
+    pub fn collect_protocol_fees(e: Env) -> Result<(i128, i128), PoolError> {
+        let recipient = get_protocol_fee_recipient(&e).ok_or(PoolError::NoFeeRecipient)?;
+        ttl::extend_instance(&e);
+        let (fees_a, fees_b) = (get_protocol_fees_a(&e), get_protocol_fees_b(&e));
+
+        put_protocol_fees_a(&e, 0);
+        put_protocol_fees_b(&e, 0);
+        if fees_a > 0 {
+            transfer_a(&e, recipient.clone(), fees_a);
+        }
+        if fees_b > 0 {
+            transfer_b(&e, recipient, fees_b);
+        }
+
+        Ok((fees_a, fees_b))
+    }
+
+    /// Set the reserves to the pool-owned balances, taking in tokens sent to
+    /// the pool outside of `deposit` and `swap`. Returns the new reserves.
+    pub fn sync(e: Env) -> Result<(i128, i128), PoolError> {
+        ttl::extend_instance(&e);
+        check_not_paused(&e)?;
+
+        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
+        if balance_a <= 0 || balance_b <= 0 {
+            return Err(PoolError::NonPositiveReserves);
+        }
+
+        put_reserves(&e, balance_a, balance_b);
+
+        events::sync(&e, balance_a, balance_b);
+        Ok((balance_a, balance_b))
+    }
+
+    /// Send `to` whatever the pool holds above its reserves and accrued
+    /// protocol fees. Returns the amounts sent.
+    pub fn skim(e: Env, to: Address) -> (i128, i128) {
+        ttl::extend_instance(&e);
+
+        let excess_a = (get_balance_a(&e) - get_reserve_a(&e)).max(0);
+        let excess_b = (get_balance_b(&e) - get_reserve_b(&e)).max(0);
+        if excess_a > 0 {
+            transfer_a(&e, to.clone(), excess_a);
+        }
+        if excess_b > 0 {
+            transfer_b(&e, to.clone(), excess_b);
+        }
+
+        events::skim(&e, to, excess_a, excess_b);
+        (excess_a, excess_b)
+    }
+
+    /// Get the swap fee in basis points
+    pub fn get_fee(e: Env) -> u32 {
+        get_fee_bps(&e)
+    }
+
+    /// Get the protocol fee recipient and its share of the swap fee in basis
+    /// points
+    pub fn get_protocol_fee(e: Env) -> (Option<Address>, u32) {
+        (get_protocol_fee_recipient(&e), get_protocol_fee_bps(&e))
+    }
+
+    /// Get protocol fees accrued and not yet collected
+    pub fn get_protocol_fees_accrued(e: Env) -> (i128, i128) {
+        (get_protocol_fees_a(&e), get_protocol_fees_b(&e))
+    }
+
+    /// Get admin address
+    pub fn get_admin(e: Env) -> Address {
+        get_admin(&e)
+    }
+
+    /// Get guardian address
+    pub fn get_guardian(e: Env) -> Option<Address> {
+        get_guardian(&e)
+    }
+
+    /// Check if swaps and deposits are paused
+    pub fn is_paused(e: Env) -> bool {
+        is_paused(&e)
     }
 
     /// Get current reserves
@@ -319,6 +945,12 @@ impl LiquidityPool {
         (get_reserve_a(&e), get_reserve_b(&e))
     }
 
+    /// Get the token balances owned by the pool, excluding accrued protocol
+    /// fees. They exceed `get_reserves` by what `skim` would send out
+    pub fn get_balances(e: Env) -> (i128, i128) {
+        (get_balance_a(&e), get_balance_b(&e))
+    }
+
     /// Get token addresses
     pub fn get_tokens(e: Env) -> (Address, Address) {
         (get_token_a(&e), get_token_b(&e))
@@ -0,0 +1,47 @@
+//! Overflow-safe arithmetic for the pricing paths, on top of the 256-bit
+//! helpers of `wide_math`.
+
+use core::cmp::Ordering;
+use wide_math::{wide_div, wide_mul};
+
+pub(crate) use wide_math::mul_div;
+
+/// Compares `a * b` with `c * d` without overflowing
+pub(crate) fn cmp_products(a: i128, b: i128, c: i128, d: i128) -> Ordering {
+    let sign = |x: i128, y: i128| (x.signum() * y.signum()).cmp(&0);
+    let (left_sign, right_sign) = (sign(a, b), sign(c, d));
+    if left_sign != right_sign {
+        return left_sign.cmp(&right_sign);
+    }
+
+    let left = wide_mul(a.unsigned_abs(), b.unsigned_abs());
+    let right = wide_mul(c.unsigned_abs(), d.unsigned_abs());
+    match left_sign {
+        Ordering::Less => right.cmp(&left),
+        _ => left.cmp(&right),
+    }
+}
+
+/// Integer square root of `a * b`, for non-negative `a` and `b`
+pub(crate) fn sqrt_product(a: i128, b: i128) -> i128 {
+    if a <= 0 || b <= 0 {
+        return 0;
+    }
+
+    let (a, b) = (a as u128, b as u128);
+    let n = wide_mul(a, b);
+
+    // Newton's method, starting from the arithmetic mean, which is never
+    // below the square root. Every step stays at or above the root, so
+    // `n / x` is at most the root and fits in u128
+    let mut x = a / 2 + b / 2 + (a & b & 1);
+    loop {
+        let (quotient, _) = wide_div(n, x).unwrap();
+        let y = x / 2 + quotient / 2 + (x & quotient & 1);
+        if y >= x {
+            // The root is at most max(a, b), so it fits in i128
+            return x as i128;
+        }
+        x = y;
+    }
+}
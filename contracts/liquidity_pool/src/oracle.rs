@@ -0,0 +1,191 @@
+//! Time-weighted average price oracle. Every reserve change first adds the
+//! prices in effect since the previous change, times the seconds they lasted,
+//! to cumulative sums. A spot price moved within one ledger lasts no time and
+//! so barely moves the averages taken from these sums.
+//!
+//! Prices are 18-decimal fixed point: `price_a` is the amount of token B
+//! paid for one token A, times `PRICE_SCALE`, and `price_b` the reverse. The
+//! sums wrap around on overflow, which keeps differences between them exact
+//! as long as the true difference fits in a u128.
+
+use soroban_sdk::{contractimpl, contracttype, Env, Vec};
+
+use crate::{get_reserve_a, get_reserve_b, ttl, DataKey, PoolError};
+use crate::{LiquidityPool, LiquidityPoolArgs, LiquidityPoolClient};
+
+/// Fixed point scale of the prices returned by the oracle
+pub const PRICE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// Minimum seconds between two stored observations
+const OBSERVATION_PERIOD: u64 = 1_800;
+
+/// Observations kept, so `consult` covers windows of up to a day
+const OBSERVATION_CAPACITY: u32 = 48;
+
+/// Cumulative prices as of the last reserve change
+#[derive(Clone)]
+#[contracttype]
+pub(crate) struct OracleState {
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+    pub updated_at: u64,
+    /// Timestamp of the newest stored observation
+    pub observed_at: Option<u64>,
+    /// Slot of the ring buffer the next observation goes to
+    pub next_observation: u32,
+}
+
+/// Cumulative prices at `timestamp`, kept in a ring buffer for `consult`
+#[derive(Clone)]
+#[contracttype]
+pub(crate) struct Observation {
+    pub timestamp: u64,
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+}
+
+/// `numerator / denominator` scaled by `PRICE_SCALE`, saturating at
+/// `u128::MAX`
+fn price(numerator: i128, denominator: i128) -> u128 {
+    wide_math::mul_div_u128(numerator as u128, PRICE_SCALE, denominator as u128)
+        .unwrap_or(u128::MAX)
+}
+
+/// The prices accumulated up to `now`, with `reserve_a` and `reserve_b`
+/// in effect since the last update
+fn accumulate(state: &OracleState, reserve_a: i128, reserve_b: i128, now: u64) -> (u128, u128) {
+    let elapsed = now.saturating_sub(state.updated_at) as u128;
+    if elapsed == 0 || reserve_a <= 0 || reserve_b <= 0 {
+        return (state.price_a_cumulative, state.price_b_cumulative);
+    }
+    (
+        state
+            .price_a_cumulative
+            .wrapping_add(price(reserve_b, reserve_a).wrapping_mul(elapsed)),
+        state
+            .price_b_cumulative
+            .wrapping_add(price(reserve_a, reserve_b).wrapping_mul(elapsed)),
+    )
+}
+
+fn get_state(e: &Env) -> Option<OracleState> {
+    e.storage().instance().get(&DataKey::Oracle)
+}
+
+fn get_observations(e: &Env) -> Vec<Observation> {
+    let key = DataKey::Observations;
+    match e.storage().persistent().get(&key) {
+        Some(observations) => {
+            ttl::extend_persistent(e, &key);
+            observations
+        }
+        None => Vec::new(e),
+    }
+}
+
+/// Accumulates the prices of the current reserves up to now. Must run before
+/// every reserve change
+pub(crate) fn update(e: &Env) {
+    let now = e.ledger().timestamp();
+    let mut state = match get_state(e) {
+        Some(state) => state,
+        // Pools start accumulating on their first reserve change, including
+        // pools upgraded from a wasm without the oracle
+        None => OracleState {
+            price_a_cumulative: 0,
+            price_b_cumulative: 0,
+            updated_at: now,
+            observed_at: None,
+            next_observation: 0,
+        },
+    };
+
+    let (price_a_cumulative, price_b_cumulative) =
+        accumulate(&state, get_reserve_a(e), get_reserve_b(e), now);
+    state.price_a_cumulative = price_a_cumulative;
+    state.price_b_cumulative = price_b_cumulative;
+    state.updated_at = now;
+
+    if state
+        .observed_at
+        .is_none_or(|observed_at| now - observed_at >= OBSERVATION_PERIOD)
+    {
+        let mut observations = get_observations(e);
+        let observation = Observation {
+            timestamp: now,
+            price_a_cumulative,
+            price_b_cumulative,
+        };
+        if observations.len() < OBSERVATION_CAPACITY {
+            observations.push_back(observation);
+        } else {
+            observations.set(state.next_observation, observation);
+        }
+        state.observed_at = Some(now);
+        state.next_observation = (state.next_observation + 1) % OBSERVATION_CAPACITY;
+
+        e.storage()
+            .persistent()
+            .set(&DataKey::Observations, &observations);
+        ttl::extend_persistent(e, &DataKey::Observations);
+    }
+
+    e.storage().instance().set(&DataKey::Oracle, &state);
+}
+
+/// The cumulative prices as of now, with the current reserves in effect
+/// since the last update
+fn current_cumulative_prices(e: &Env) -> (u128, u128, u64) {
+    let now = e.ledger().timestamp();
+    match get_state(e) {
+        Some(state) => {
+            let (price_a, price_b) = accumulate(&state, get_reserve_a(e), get_reserve_b(e), now);
+            (price_a, price_b, now)
+        }
+        None => (0, 0, now),
+    }
+}
+
+#[contractimpl]
+impl LiquidityPool {
+    /// Get the cumulative prices of token A and token B and the timestamp
+    /// they are taken at, which is the current one. The average price between
+    /// two calls is the difference between their cumulative prices, wrapping
+    /// on overflow, divided by the seconds between them
+    pub fn get_cumulative_prices(e: Env) -> (u128, u128, u64) {
+        current_cumulative_prices(&e)
+    }
+
+    /// Get the time-weighted average prices of token A and token B over at
+    /// least the last `window_seconds`, scaled by `PRICE_SCALE`. The average
+    /// starts at the newest stored observation old enough, so it may cover up
+    /// to half an hour more than asked
+    pub fn consult(e: Env, window_seconds: u64) -> Result<(u128, u128), PoolError> {
+        if window_seconds == 0 {
+            return Err(PoolError::InvalidWindow);
+        }
+
+        let (price_a_cumulative, price_b_cumulative, now) = current_cumulative_prices(&e);
+        let start = now
+            .checked_sub(window_seconds)
+            .ok_or(PoolError::NotEnoughHistory)?;
+
+        let mut newest_before_start: Option<Observation> = None;
+        for observation in get_observations(&e).iter() {
+            if observation.timestamp <= start
+                && newest_before_start
+                    .as_ref()
+                    .is_none_or(|newest| observation.timestamp > newest.timestamp)
+            {
+                newest_before_start = Some(observation);
+            }
+        }
+        let observation = newest_before_start.ok_or(PoolError::NotEnoughHistory)?;
+
+        let elapsed = (now - observation.timestamp) as u128;
+        Ok((
+            price_a_cumulative.wrapping_sub(observation.price_a_cumulative) / elapsed,
+            price_b_cumulative.wrapping_sub(observation.price_b_cumulative) / elapsed,
+        ))
+    }
+}
@@ -0,0 +1,177 @@
+//! SEP-41 token interface over the pool shares, so LP positions can be
+//! transferred and approved like any other token.
+
+use soroban_sdk::{
+    contractimpl, contracttype, panic_with_error, symbol_short, token::TokenInterface, Address,
+    Env, String,
+};
+
+use crate::{get_shares, get_total_shares, put_shares, put_total_shares, ttl, DataKey, PoolError};
+use crate::{LiquidityPool, LiquidityPoolArgs, LiquidityPoolClient};
+
+/// Shares are minted as `isqrt(amount_a * amount_b)`, so they carry the same
+/// precision as Stellar assets
+const SHARE_DECIMALS: u32 = 7;
+
+#[derive(Clone)]
+#[contracttype]
+pub struct AllowanceDataKey {
+    pub from: Address,
+    pub spender: Address,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct AllowanceValue {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+fn check_nonnegative_amount(e: &Env, amount: i128) {
+    if amount < 0 {
+        panic_with_error!(e, PoolError::NegativeAmount);
+    }
+}
+
+fn read_allowance(e: &Env, from: Address, spender: Address) -> AllowanceValue {
+    let key = DataKey::Allowance(AllowanceDataKey { from, spender });
+    match e.storage().temporary().get::<_, AllowanceValue>(&key) {
+        Some(allowance) if allowance.expiration_ledger >= e.ledger().sequence() => allowance,
+        _ => AllowanceValue {
+            amount: 0,
+            expiration_ledger: 0,
+        },
+    }
+}
+
+fn write_allowance(e: &Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+    if amount > 0 && expiration_ledger < e.ledger().sequence() {
+        panic_with_error!(e, PoolError::InvalidExpiration);
+    }
+
+    let key = DataKey::Allowance(AllowanceDataKey { from, spender });
+    e.storage().temporary().set(
+        &key,
+        &AllowanceValue {
+            amount,
+            expiration_ledger,
+        },
+    );
+
+    if amount > 0 {
+        let live_for = expiration_ledger - e.ledger().sequence();
+        e.storage().temporary().extend_ttl(&key, live_for, live_for);
+    }
+}
+
+fn spend_allowance(e: &Env, from: Address, spender: Address, amount: i128) {
+    let allowance = read_allowance(e, from.clone(), spender.clone());
+    if allowance.amount < amount {
+        panic_with_error!(e, PoolError::InsufficientAllowance);
+    }
+    if amount > 0 {
+        write_allowance(
+            e,
+            from,
+            spender,
+            allowance.amount - amount,
+            allowance.expiration_ledger,
+        );
+    }
+}
+
+fn move_shares(e: &Env, from: &Address, to: &Address, amount: i128) {
+    let from_shares = get_shares(e, from);
+    if from_shares < amount {
+        panic_with_error!(e, PoolError::InsufficientShares);
+    }
+    put_shares(e, from, from_shares - amount);
+    put_shares(e, to, get_shares(e, to) + amount);
+
+    e.events().publish(
+        (symbol_short!("transfer"), from.clone(), to.clone()),
+        amount,
+    );
+}
+
+fn destroy_shares(e: &Env, from: &Address, amount: i128) {
+    let from_shares = get_shares(e, from);
+    if from_shares < amount {
+        panic_with_error!(e, PoolError::InsufficientShares);
+    }
+    put_shares(e, from, from_shares - amount);
+    put_total_shares(e, get_total_shares(e) - amount);
+
+    e.events()
+        .publish((symbol_short!("burn"), from.clone()), amount);
+}
+
+#[contractimpl]
+impl TokenInterface for LiquidityPool {
+    fn allowance(e: Env, from: Address, spender: Address) -> i128 {
+        read_allowance(&e, from, spender).amount
+    }
+
+    fn approve(e: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+        check_nonnegative_amount(&e, amount);
+        ttl::extend_instance(&e);
+
+        write_allowance(&e, from.clone(), spender.clone(), amount, expiration_ledger);
+        e.events().publish(
+            (symbol_short!("approve"), from, spender),
+            (amount, expiration_ledger),
+        );
+    }
+
+    fn balance(e: Env, id: Address) -> i128 {
+        get_shares(&e, &id)
+    }
+
+    fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        check_nonnegative_amount(&e, amount);
+        ttl::extend_instance(&e);
+
+        move_shares(&e, &from, &to, amount);
+    }
+
+    fn transfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+        check_nonnegative_amount(&e, amount);
+        ttl::extend_instance(&e);
+
+        spend_allowance(&e, from.clone(), spender, amount);
+        move_shares(&e, &from, &to, amount);
+    }
+
+    /// Burning shares gives up the underlying tokens to the remaining LPs
+    fn burn(e: Env, from: Address, amount: i128) {
+        from.require_auth();
+        check_nonnegative_amount(&e, amount);
+        ttl::extend_instance(&e);
+
+        destroy_shares(&e, &from, amount);
+    }
+
+    fn burn_from(e: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+        check_nonnegative_amount(&e, amount);
+        ttl::extend_instance(&e);
+
+        spend_allowance(&e, from.clone(), spender, amount);
+        destroy_shares(&e, &from, amount);
+    }
+
+    fn decimals(_e: Env) -> u32 {
+        SHARE_DECIMALS
+    }
+
+    fn name(e: Env) -> String {
+        String::from_str(&e, "Liquidity Pool Share")
+    }
+
+    fn symbol(e: Env) -> String {
+        String::from_str(&e, "LPS")
+    }
+}
@@ -1,14 +1,33 @@
 #![cfg(test)]
 extern crate std;
 
-use crate::LiquidityPoolClient;
+use crate::{
+    math, oracle::Observation, DataKey, DepositEvent, LiquidityPoolClient, PoolError, SwapEvent,
+    TtlConfig, WithdrawEvent, PRICE_SCALE,
+};
 
 use soroban_sdk::{
     symbol_short,
-    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation},
-    token, Address, Env, IntoVal,
+    testutils::{
+        storage::{Instance as _, Persistent as _},
+        Address as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger,
+    },
+    token, vec, Address, Env, IntoVal, String, Val, Vec,
 };
 
+use ethnum::I256;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// The pool as built by `stellar contract build`, to upgrade to
+mod liqpool_wasm {
+    soroban_sdk::contractimport!(file = "../../target/wasm32v1-none/release/liquidity_pool.wasm");
+}
+
+// The first upgradeable release of the pool, pinned to upgrade from
+mod liqpool_v1 {
+    soroban_sdk::contractimport!(file = "fixtures/liquidity_pool_v1.wasm");
+}
+
 fn create_token_contract<'a>(
     e: &Env,
     admin: &Address,
@@ -25,7 +44,31 @@ fn create_liqpool_contract<'a>(
     token_a: &Address,
     token_b: &Address,
 ) -> LiquidityPoolClient<'a> {
-    LiquidityPoolClient::new(e, &e.register(crate::LiquidityPool {}, (token_a, token_b)))
+    create_liqpool_contract_with_fee(e, token_a, token_b, &Address::generate(e), 30)
+}
+
+fn create_liqpool_contract_with_fee<'a>(
+    e: &Env,
+    token_a: &Address,
+    token_b: &Address,
+    admin: &Address,
+    fee_bps: u32,
+) -> LiquidityPoolClient<'a> {
+    LiquidityPoolClient::new(
+        e,
+        &e.register(crate::LiquidityPool {}, (token_a, token_b, admin, fee_bps)),
+    )
+}
+
+/// Events published by `contract` during the last invocation
+fn contract_events(e: &Env, contract: &Address) -> Vec<(Address, Vec<Val>, Val)> {
+    let mut events = Vec::new(e);
+    for event in e.events().all().iter() {
+        if event.0 == *contract {
+            events.push_back(event);
+        }
+    }
+    events
 }
 
 #[test]
@@ -42,13 +85,13 @@ fn test() {
 
     let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
 
-    token1_admin.mint(&user1, &1000);
-    assert_eq!(token1.balance(&user1), 1000);
+    token1_admin.mint(&user1, &100_000);
+    assert_eq!(token1.balance(&user1), 100_000);
 
-    token2_admin.mint(&user1, &1000);
-    assert_eq!(token2.balance(&user1), 1000);
+    token2_admin.mint(&user1, &100_000);
+    assert_eq!(token2.balance(&user1), 100_000);
 
-    liqpool.deposit(&user1, &100, &100, &100, &100);
+    liqpool.deposit(&user1, &10_000, &10_000, &10_000, &10_000);
     assert_eq!(
         e.auths(),
         std::vec![(
@@ -57,14 +100,14 @@ fn test() {
                 function: AuthorizedFunction::Contract((
                     liqpool.address.clone(),
                     symbol_short!("deposit"),
-                    (&user1, 100_i128, 100_i128, 100_i128, 100_i128).into_val(&e)
+                    (&user1, 10_000_i128, 10_000_i128, 10_000_i128, 10_000_i128).into_val(&e)
                 )),
                 sub_invocations: std::vec![
                     AuthorizedInvocation {
                         function: AuthorizedFunction::Contract((
                             token1.address.clone(),
                             symbol_short!("transfer"),
-                            (&user1, &liqpool.address, 100_i128).into_val(&e)
+                            (&user1, &liqpool.address, 10_000_i128).into_val(&e)
                         )),
                         sub_invocations: std::vec![]
                     },
@@ -72,7 +115,7 @@ fn test() {
                         function: AuthorizedFunction::Contract((
                             token2.address.clone(),
                             symbol_short!("transfer"),
-                            (&user1, &liqpool.address, 100_i128).into_val(&e)
+                            (&user1, &liqpool.address, 10_000_i128).into_val(&e)
                         )),
                         sub_invocations: std::vec![]
                     }
@@ -81,13 +124,15 @@ fn test() {
         )]
     );
 
-    assert_eq!(liqpool.balance_shares(&user1), 100);
-    assert_eq!(token1.balance(&user1), 900);
-    assert_eq!(token1.balance(&liqpool.address), 100);
-    assert_eq!(token2.balance(&user1), 900);
-    assert_eq!(token2.balance(&liqpool.address), 100);
+    // The first 1000 shares stay locked in the pool
+    assert_eq!(liqpool.balance_shares(&user1), 9_000);
+    assert_eq!(liqpool.balance_shares(&liqpool.address), 1_000);
+    assert_eq!(token1.balance(&user1), 90_000);
+    assert_eq!(token1.balance(&liqpool.address), 10_000);
+    assert_eq!(token2.balance(&user1), 90_000);
+    assert_eq!(token2.balance(&liqpool.address), 10_000);
 
-    liqpool.swap(&user1, &false, &49, &100);
+    liqpool.swap(&user1, &false, &4_900, &10_000);
     assert_eq!(
         e.auths(),
         std::vec![(
@@ -96,13 +141,13 @@ fn test() {
                 function: AuthorizedFunction::Contract((
                     liqpool.address.clone(),
                     symbol_short!("swap"),
-                    (&user1, false, 49_i128, 100_i128).into_val(&e)
+                    (&user1, false, 4_900_i128, 10_000_i128).into_val(&e)
                 )),
                 sub_invocations: std::vec![AuthorizedInvocation {
                     function: AuthorizedFunction::Contract((
                         token1.address.clone(),
                         symbol_short!("transfer"),
-                        (&user1, &liqpool.address, 97_i128).into_val(&e)
+                        (&user1, &liqpool.address, 9_637_i128).into_val(&e)
                     )),
                     sub_invocations: std::vec![]
                 }]
@@ -110,13 +155,12 @@ fn test() {
         )]
     );
 
-    assert_eq!(token1.balance(&user1), 803);
-    assert_eq!(token1.balance(&liqpool.address), 197);
-    assert_eq!(token2.balance(&user1), 949);
-    assert_eq!(token2.balance(&liqpool.address), 51);
+    assert_eq!(token1.balance(&user1), 80_363);
+    assert_eq!(token1.balance(&liqpool.address), 19_637);
+    assert_eq!(token2.balance(&user1), 94_900);
+    assert_eq!(token2.balance(&liqpool.address), 5_100);
 
-    e.cost_estimate().budget().reset_unlimited();
-    liqpool.withdraw(&user1, &100, &197, &51);
+    liqpool.withdraw(&user1, &9_000, &17_673, &4_590);
 
     assert_eq!(
         e.auths(),
@@ -126,18 +170,21 @@ fn test() {
                 function: AuthorizedFunction::Contract((
                     liqpool.address.clone(),
                     symbol_short!("withdraw"),
-                    (&user1, 100_i128, 197_i128, 51_i128).into_val(&e)
+                    (&user1, 9_000_i128, 17_673_i128, 4_590_i128).into_val(&e)
                 )),
                 sub_invocations: std::vec![]
             }
         )]
     );
 
-    assert_eq!(token1.balance(&user1), 1000);
-    assert_eq!(token2.balance(&user1), 1000);
+    assert_eq!(token1.balance(&user1), 98_036);
+    assert_eq!(token2.balance(&user1), 99_490);
     assert_eq!(liqpool.balance_shares(&user1), 0);
-    assert_eq!(token1.balance(&liqpool.address), 0);
-    assert_eq!(token2.balance(&liqpool.address), 0);
+
+    // The last LP cannot drain the pool, the locked shares keep their part
+    assert_eq!(token1.balance(&liqpool.address), 1_964);
+    assert_eq!(token2.balance(&liqpool.address), 510);
+    assert_eq!(liqpool.get_total_shares(), 1_000);
 }
 
 #[test]
@@ -195,3 +242,1007 @@ fn swap_reserve_one_nonzero_other_zero() {
     token2.transfer(&user1, &liqpool.address, &1);
     liqpool.swap(&user1, &false, &1, &1);
 }
+
+fn setup_funded_pool<'a>(e: &Env) -> (LiquidityPoolClient<'a>, Address) {
+    let admin1 = Address::generate(e);
+    let admin2 = Address::generate(e);
+
+    let (token1, token1_admin) = create_token_contract(e, &admin1);
+    let (token2, token2_admin) = create_token_contract(e, &admin2);
+    let liqpool = create_liqpool_contract(e, &token1.address, &token2.address);
+
+    let user1 = Address::generate(e);
+    token1_admin.mint(&user1, &100_000);
+    token2_admin.mint(&user1, &100_000);
+    liqpool.deposit(&user1, &10_000, &10_000, &10_000, &10_000);
+
+    (liqpool, user1)
+}
+
+#[test]
+fn deposit_errors_are_typed() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+
+    // amount_b = 5_000, below min_b
+    assert_eq!(
+        liqpool.try_deposit(&user1, &5_000, &0, &10_000, &6_000),
+        Err(Ok(PoolError::AmountBBelowMin))
+    );
+    // amount_a = 5_000, below min_a
+    assert_eq!(
+        liqpool.try_deposit(&user1, &10_000, &6_000, &5_000, &0),
+        Err(Ok(PoolError::AmountAInvalid))
+    );
+    assert_eq!(
+        liqpool.try_deposit(&user1, &0, &0, &0, &0),
+        Err(Ok(PoolError::NonPositiveAmounts))
+    );
+}
+
+#[test]
+fn swap_errors_are_typed() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+
+    assert_eq!(
+        liqpool.try_swap(&user1, &false, &10_001, &100_000),
+        Err(Ok(PoolError::InsufficientReserve))
+    );
+    // Buying 4_900 requires 9_637 in
+    assert_eq!(
+        liqpool.try_swap(&user1, &false, &4_900, &9_636),
+        Err(Ok(PoolError::InMaxExceeded))
+    );
+    // Draining the whole reserve is never possible
+    assert_eq!(
+        liqpool.try_swap(&user1, &false, &10_000, &100_000),
+        Err(Ok(PoolError::InsufficientReserve))
+    );
+    // Non-positive outputs are rejected before reaching the token contracts
+    assert_eq!(
+        liqpool.try_swap(&user1, &false, &-10, &100_000),
+        Err(Ok(PoolError::NonPositiveAmounts))
+    );
+    assert_eq!(
+        liqpool.try_swap(&user1, &true, &0, &100_000),
+        Err(Ok(PoolError::NonPositiveAmounts))
+    );
+    assert_eq!(
+        liqpool.try_quote_in(&true, &0),
+        Err(Ok(PoolError::NonPositiveAmounts))
+    );
+}
+
+#[test]
+fn withdraw_errors_are_typed() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+
+    assert_eq!(
+        liqpool.try_withdraw(&user1, &9_001, &0, &0),
+        Err(Ok(PoolError::InsufficientShares))
+    );
+    assert_eq!(
+        liqpool.try_withdraw(&user1, &9_000, &9_001, &0),
+        Err(Ok(PoolError::MinNotSatisfied))
+    );
+    for share_amount in [0, -1_000] {
+        assert_eq!(
+            liqpool.try_withdraw(&user1, &share_amount, &0, &0),
+            Err(Ok(PoolError::NonPositiveAmounts))
+        );
+        assert_eq!(
+            liqpool.try_quote_withdraw(&share_amount),
+            Err(Ok(PoolError::NonPositiveAmounts))
+        );
+    }
+    assert_eq!(liqpool.balance_shares(&user1), 9_000);
+}
+
+#[test]
+fn swap_on_empty_pool_returns_insufficient_reserve() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, _) = create_token_contract(&e, &admin1);
+    let (token2, _) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    let user1 = Address::generate(&e);
+    assert_eq!(
+        liqpool.try_swap(&user1, &false, &1, &1),
+        Err(Ok(PoolError::InsufficientReserve))
+    );
+}
+
+#[test]
+fn events_are_published() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    assert_eq!(
+        contract_events(&e, &liqpool.address),
+        vec![
+            &e,
+            // The first deposit also mints the locked minimum liquidity to
+            // the pool itself
+            (
+                liqpool.address.clone(),
+                (symbol_short!("mint"), liqpool.address.clone()).into_val(&e),
+                1_000_i128.into_val(&e)
+            ),
+            (
+                liqpool.address.clone(),
+                (symbol_short!("mint"), user1.clone()).into_val(&e),
+                9_000_i128.into_val(&e)
+            ),
+            (
+                liqpool.address.clone(),
+                (symbol_short!("deposit"), user1.clone()).into_val(&e),
+                DepositEvent {
+                    amount_a: 10_000,
+                    amount_b: 10_000,
+                    shares_minted: 9_000,
+                }
+                .into_val(&e)
+            ),
+            (
+                liqpool.address.clone(),
+                (symbol_short!("sync"),).into_val(&e),
+                (10_000_i128, 10_000_i128).into_val(&e)
+            ),
+        ]
+    );
+
+    liqpool.swap(&user1, &false, &4_900, &10_000);
+    assert_eq!(
+        contract_events(&e, &liqpool.address),
+        vec![
+            &e,
+            (
+                liqpool.address.clone(),
+                (symbol_short!("swap"), user1.clone(), false).into_val(&e),
+                SwapEvent {
+                    amount_in: 9_637,
+                    amount_out: 4_900,
+                }
+                .into_val(&e)
+            ),
+            (
+                liqpool.address.clone(),
+                (symbol_short!("sync"),).into_val(&e),
+                (19_637_i128, 5_100_i128).into_val(&e)
+            ),
+        ]
+    );
+
+    liqpool.withdraw(&user1, &9_000, &17_673, &4_590);
+    assert_eq!(
+        contract_events(&e, &liqpool.address),
+        vec![
+            &e,
+            (
+                liqpool.address.clone(),
+                (symbol_short!("burn"), user1.clone()).into_val(&e),
+                9_000_i128.into_val(&e)
+            ),
+            (
+                liqpool.address.clone(),
+                (symbol_short!("withdraw"), user1.clone()).into_val(&e),
+                WithdrawEvent {
+                    amount_a: 17_673,
+                    amount_b: 4_590,
+                    shares_burned: 9_000,
+                }
+                .into_val(&e)
+            ),
+            (
+                liqpool.address.clone(),
+                (symbol_short!("sync"),).into_val(&e),
+                (1_964_i128, 510_i128).into_val(&e)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn swap_exact_in() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    let (token_a, token_b) = liqpool.get_tokens();
+    let (token1, token2) = (
+        token::Client::new(&e, &token_a),
+        token::Client::new(&e, &token_b),
+    );
+
+    // 9_637 * 9_970 * 10_000 / (10_000 * 10_000 + 9_637 * 9_970) = 4_900
+    assert_eq!(liqpool.swap_exact_in(&user1, &true, &9_637, &4_900), 4_900);
+    assert_eq!(token1.balance(&user1), 80_363);
+    assert_eq!(token2.balance(&user1), 94_900);
+    assert_eq!(liqpool.get_reserves(), (19_637, 5_100));
+
+    assert_eq!(
+        liqpool.try_swap_exact_in(&user1, &false, &1_000, &10_000),
+        Err(Ok(PoolError::OutBelowMin))
+    );
+    assert_eq!(
+        liqpool.try_swap_exact_in(&user1, &false, &0, &0),
+        Err(Ok(PoolError::NonPositiveAmounts))
+    );
+}
+
+#[test]
+fn quotes_match_execution() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+
+    assert_eq!(liqpool.quote_in(&false, &4_900), 9_637);
+    assert_eq!(
+        liqpool.try_quote_in(&false, &10_000),
+        Err(Ok(PoolError::InsufficientReserve))
+    );
+    liqpool.swap(&user1, &false, &4_900, &9_637);
+    assert_eq!(liqpool.get_reserves(), (19_637, 5_100));
+
+    let quoted_out = liqpool.quote_out(&false, &2_000);
+    assert_eq!(
+        liqpool.swap_exact_in(&user1, &false, &2_000, &0),
+        quoted_out
+    );
+    assert_eq!(
+        liqpool.try_quote_out(&true, &0),
+        Err(Ok(PoolError::NonPositiveAmounts))
+    );
+
+    let (reserve_a, reserve_b) = liqpool.get_reserves();
+    let (amount_a, amount_b, shares) = liqpool.quote_deposit(&reserve_a, &(reserve_b * 2));
+    assert_eq!((amount_a, amount_b), (reserve_a, reserve_b));
+    let shares_before = liqpool.balance_shares(&user1);
+    liqpool.deposit(&user1, &reserve_a, &0, &(reserve_b * 2), &0);
+    assert_eq!(liqpool.balance_shares(&user1), shares_before + shares);
+
+    let (out_a, out_b) = liqpool.quote_withdraw(&shares);
+    assert_eq!(liqpool.withdraw(&user1, &shares, &0, &0), (out_a, out_b));
+    assert_eq!(
+        liqpool.try_quote_withdraw(&(liqpool.get_total_shares() + 1)),
+        Err(Ok(PoolError::InsufficientShares))
+    );
+}
+
+#[test]
+fn swap_fee_is_configurable() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+
+    let admin = Address::generate(&e);
+    let liqpool =
+        create_liqpool_contract_with_fee(&e, &token1.address, &token2.address, &admin, 100);
+    assert_eq!(liqpool.get_fee(), 100);
+    assert_eq!(liqpool.get_admin(), admin);
+
+    let user1 = Address::generate(&e);
+    token1_admin.mint(&user1, &100_000);
+    token2_admin.mint(&user1, &100_000);
+    liqpool.deposit(&user1, &10_000, &10_000, &10_000, &10_000);
+
+    // 10_000 * 4_900 * 10_000 / (5_100 * 9_900) + 1 = 9_705, 68 more than
+    // with 0.3%
+    assert_eq!(liqpool.quote_in(&false, &4_900), 9_705);
+
+    liqpool.set_fee(&admin, &5);
+    assert_eq!(liqpool.get_fee(), 5);
+    assert_eq!(liqpool.quote_in(&false, &4_900), 9_613);
+
+    assert_eq!(
+        liqpool.try_set_fee(&admin, &1001),
+        Err(Ok(PoolError::InvalidFee))
+    );
+    assert_eq!(
+        liqpool.try_set_fee(&user1, &10),
+        Err(Ok(PoolError::Unauthorized))
+    );
+    liqpool.swap(&user1, &false, &4_900, &9_613);
+    assert_eq!(liqpool.get_reserves(), (19_613, 5_100));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #13)")]
+fn constructor_rejects_fee_above_max() {
+    let e = Env::default();
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, _) = create_token_contract(&e, &admin1);
+    let (token2, _) = create_token_contract(&e, &admin2);
+    create_liqpool_contract_with_fee(
+        &e,
+        &token1.address,
+        &token2.address,
+        &Address::generate(&e),
+        1001,
+    );
+}
+
+#[test]
+fn protocol_fee_accrues_to_recipient() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+
+    let admin = Address::generate(&e);
+    let treasury = Address::generate(&e);
+    let liqpool =
+        create_liqpool_contract_with_fee(&e, &token1.address, &token2.address, &admin, 30);
+
+    assert_eq!(liqpool.get_protocol_fee(), (None, 0));
+    assert_eq!(
+        liqpool.try_collect_protocol_fees(),
+        Err(Ok(PoolError::NoFeeRecipient))
+    );
+    assert_eq!(
+        liqpool.try_set_protocol_fee(&admin, &treasury, &10_001),
+        Err(Ok(PoolError::InvalidFee))
+    );
+
+    // Half of the 0.3% fee goes to the treasury
+    liqpool.set_protocol_fee(&admin, &treasury, &5000);
+    assert_eq!(liqpool.get_protocol_fee(), (Some(treasury.clone()), 5000));
+
+    let user1 = Address::generate(&e);
+    token1_admin.mint(&user1, &2_000_000);
+    token2_admin.mint(&user1, &2_000_000);
+    liqpool.deposit(&user1, &1_000_000, &0, &1_000_000, &0);
+
+    liqpool.swap_exact_in(&user1, &true, &100_000, &0);
+    assert_eq!(liqpool.get_protocol_fees_accrued(), (150, 0));
+    let (reserve_a, _) = liqpool.get_reserves();
+    assert_eq!(reserve_a, 1_100_000 - 150);
+    assert_eq!(token1.balance(&liqpool.address), 1_100_000);
+
+    assert_eq!(liqpool.collect_protocol_fees(), (150, 0));
+    assert_eq!(token1.balance(&treasury), 150);
+    assert_eq!(liqpool.get_protocol_fees_accrued(), (0, 0));
+    assert_eq!(token1.balance(&liqpool.address), reserve_a);
+}
+
+#[test]
+fn shares_are_a_sep41_token() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    let shares = token::Client::new(&e, &liqpool.address);
+    let user2 = Address::generate(&e);
+    let spender = Address::generate(&e);
+
+    assert_eq!(shares.decimals(), 7);
+    assert_eq!(shares.name(), String::from_str(&e, "Liquidity Pool Share"));
+    assert_eq!(shares.symbol(), String::from_str(&e, "LPS"));
+    assert_eq!(shares.balance(&user1), 9_000);
+
+    shares.transfer(&user1, &user2, &3_000);
+    assert_eq!(
+        contract_events(&e, &liqpool.address),
+        vec![
+            &e,
+            (
+                liqpool.address.clone(),
+                (symbol_short!("transfer"), user1.clone(), user2.clone()).into_val(&e),
+                3_000_i128.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(shares.balance(&user1), 6_000);
+    assert_eq!(shares.balance(&user2), 3_000);
+    assert_eq!(liqpool.get_total_shares(), 10_000);
+
+    shares.approve(&user1, &spender, &2_000, &(e.ledger().sequence() + 100));
+    assert_eq!(shares.allowance(&user1, &spender), 2_000);
+    shares.transfer_from(&spender, &user1, &user2, &1_500);
+    assert_eq!(shares.allowance(&user1, &spender), 500);
+    assert_eq!(shares.balance(&user2), 4_500);
+    assert_eq!(
+        liqpool.try_transfer_from(&spender, &user1, &user2, &600),
+        Err(Ok(soroban_sdk::Error::from_contract_error(
+            PoolError::InsufficientAllowance as u32
+        )))
+    );
+    assert_eq!(
+        liqpool.try_transfer(&user2, &user1, &4_501),
+        Err(Ok(soroban_sdk::Error::from_contract_error(
+            PoolError::InsufficientShares as u32
+        )))
+    );
+
+    // Transferred shares can be redeemed by their new owner
+    assert_eq!(liqpool.withdraw(&user2, &4_500, &0, &0), (4_500, 4_500));
+    assert_eq!(shares.balance(&user2), 0);
+
+    shares.burn(&user1, &500);
+    assert_eq!(shares.balance(&user1), 4_000);
+    assert_eq!(liqpool.get_total_shares(), 5_000);
+}
+
+#[test]
+fn guardian_pauses_swaps_and_deposits() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    let admin = liqpool.get_admin();
+    let guardian = Address::generate(&e);
+
+    assert_eq!(
+        liqpool.try_pause(&guardian),
+        Err(Ok(PoolError::Unauthorized))
+    );
+    assert_eq!(
+        liqpool.try_set_guardian(&guardian, &Some(guardian.clone())),
+        Err(Ok(PoolError::Unauthorized))
+    );
+    liqpool.set_guardian(&admin, &Some(guardian.clone()));
+    assert_eq!(liqpool.get_guardian(), Some(guardian.clone()));
+
+    liqpool.pause(&guardian);
+    assert_eq!(
+        contract_events(&e, &liqpool.address),
+        vec![
+            &e,
+            (
+                liqpool.address.clone(),
+                (symbol_short!("paused"), guardian.clone()).into_val(&e),
+                ().into_val(&e)
+            ),
+        ]
+    );
+    assert!(liqpool.is_paused());
+    assert_eq!(
+        liqpool.try_swap(&user1, &false, &10, &20),
+        Err(Ok(PoolError::Paused))
+    );
+    assert_eq!(
+        liqpool.try_swap_exact_in(&user1, &true, &10, &0),
+        Err(Ok(PoolError::Paused))
+    );
+    assert_eq!(
+        liqpool.try_deposit(&user1, &10, &10, &10, &10),
+        Err(Ok(PoolError::Paused))
+    );
+
+    // LPs can always get their funds out
+    assert_eq!(liqpool.withdraw(&user1, &50, &50, &50), (50, 50));
+
+    liqpool.unpause(&admin);
+    assert!(!liqpool.is_paused());
+    liqpool.swap_exact_in(&user1, &true, &10, &0);
+
+    liqpool.set_guardian(&admin, &None);
+    assert_eq!(
+        liqpool.try_pause(&guardian),
+        Err(Ok(PoolError::Unauthorized))
+    );
+}
+
+#[test]
+fn upgrade_keeps_state() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &Address::generate(&e));
+    let (token2, token2_admin) = create_token_contract(&e, &Address::generate(&e));
+    let address = e.register(
+        liqpool_v1::WASM,
+        (&token1.address, &token2.address, &admin, 30_u32),
+    );
+    let old = liqpool_v1::Client::new(&e, &address);
+
+    let user1 = Address::generate(&e);
+    token1_admin.mint(&user1, &100_000);
+    token2_admin.mint(&user1, &100_000);
+    old.deposit(&user1, &10_000, &10_000, &10_000, &10_000);
+    old.swap_exact_in(&user1, &true, &10, &0);
+    let reserves = old.get_reserves();
+    let quote = old.quote_withdraw(&5_000);
+    assert_eq!(old.get_version(), 1);
+
+    let new_wasm_hash = e.deployer().upload_contract_wasm(liqpool_wasm::WASM);
+    assert_eq!(
+        old.try_upgrade(&user1, &new_wasm_hash),
+        Err(Ok(liqpool_v1::PoolError::Unauthorized))
+    );
+    old.upgrade(&admin, &new_wasm_hash);
+    assert_eq!(
+        contract_events(&e, &address),
+        vec![
+            &e,
+            (
+                address.clone(),
+                (symbol_short!("upgrade"), admin.clone()).into_val(&e),
+                new_wasm_hash.into_val(&e)
+            ),
+        ]
+    );
+
+    let upgraded = liqpool_wasm::Client::new(&e, &address);
+    assert_eq!(upgraded.get_reserves(), reserves);
+    assert_eq!(upgraded.balance(&user1), 10_000);
+    assert_eq!(upgraded.get_admin(), admin);
+    assert_eq!(upgraded.get_fee(), 30);
+    assert_eq!(upgraded.get_version(), 1);
+    assert_eq!(upgraded.quote_withdraw(&5_000), quote);
+
+    // Unlike the old `upgrade`, the new one keeps the instance live
+    let config = upgraded.get_ttl_config();
+    e.as_contract(&address, || {
+        assert!(e.storage().instance().get_ttl() < config.instance_threshold)
+    });
+    upgraded.upgrade(&admin, &new_wasm_hash);
+    e.as_contract(&address, || {
+        assert_eq!(e.storage().instance().get_ttl(), config.instance_extend_to)
+    });
+
+    // Trading goes on and the oracle starts with the first reserve change
+    e.ledger().with_mut(|l| l.timestamp += 100);
+    upgraded.swap_exact_in(&user1, &false, &10, &0);
+    e.ledger().with_mut(|l| l.timestamp += 3_600);
+    let (price_a, price_b) = upgraded.consult(&3_600);
+    assert!(price_a > 0 && price_b > 0);
+
+    // LPs can still get out after the upgrade
+    let (out_a, out_b) = upgraded.quote_withdraw(&5_000);
+    assert_eq!(upgraded.withdraw(&user1, &5_000, &0, &0), (out_a, out_b));
+}
+
+#[test]
+fn storage_ttl_is_extended() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    let admin = liqpool.get_admin();
+    let config = liqpool.get_ttl_config();
+    let ttls = |user: &Address| {
+        e.as_contract(&liqpool.address, || {
+            (
+                e.storage().instance().get_ttl(),
+                e.storage()
+                    .persistent()
+                    .get_ttl(&DataKey::Shares(user.clone())),
+            )
+        })
+    };
+    assert_eq!(
+        ttls(&user1),
+        (config.instance_extend_to, config.persistent_extend_to)
+    );
+
+    // Bumping every few days keeps entries live well past their first TTL
+    let nobody = Address::generate(&e);
+    for _ in 0..6 {
+        e.ledger().with_mut(|li| li.sequence_number += 6 * 17_280);
+        liqpool.bump(&user1);
+        liqpool.bump(&nobody);
+        assert_eq!(
+            ttls(&user1),
+            (config.instance_extend_to, config.persistent_extend_to)
+        );
+    }
+    assert!(e.ledger().sequence() > config.persistent_extend_to);
+    assert_eq!(liqpool.balance_shares(&user1), 9_000);
+    let observations_ttl = e.as_contract(&liqpool.address, || {
+        e.storage().persistent().get_ttl(&DataKey::Observations)
+    });
+    assert_eq!(observations_ttl, config.persistent_extend_to);
+    assert_eq!(liqpool.balance_shares(&nobody), 0);
+
+    let invalid = TtlConfig {
+        persistent_threshold: config.persistent_extend_to + 1,
+        ..config.clone()
+    };
+    assert_eq!(
+        liqpool.try_set_ttl_config(&admin, &invalid),
+        Err(Ok(PoolError::InvalidTtl))
+    );
+    let invalid = TtlConfig {
+        instance_extend_to: e.storage().max_ttl() + 1,
+        ..config.clone()
+    };
+    assert_eq!(
+        liqpool.try_set_ttl_config(&admin, &invalid),
+        Err(Ok(PoolError::InvalidTtl))
+    );
+    assert_eq!(
+        liqpool.try_set_ttl_config(&user1, &config),
+        Err(Ok(PoolError::Unauthorized))
+    );
+
+    // Touching a balance extends it to the configured TTL
+    let longer = TtlConfig {
+        persistent_threshold: 59 * 17_280,
+        persistent_extend_to: 60 * 17_280,
+        ..config.clone()
+    };
+    liqpool.set_ttl_config(&admin, &longer);
+    assert_eq!(liqpool.get_ttl_config(), longer);
+    liqpool.balance_shares(&user1);
+    assert_eq!(ttls(&user1).1, longer.persistent_extend_to);
+}
+
+#[test]
+fn math_is_exact_on_large_operands() {
+    // Operands spread over every magnitude up to i128::MAX
+    let mut rng = StdRng::seed_from_u64(22);
+    let mut operand = |min: i128| {
+        let max = i128::MAX >> rng.gen_range(0..127);
+        rng.gen_range(min..=max)
+    };
+    for _ in 0..10_000 {
+        let (a, b, d) = (operand(0), operand(0), operand(1));
+
+        // Same result as i128 whenever the product fits
+        if let Some(n) = a.checked_mul(b) {
+            assert_eq!(math::mul_div(a, b, d), Some(n / d));
+            assert_eq!(math::mul_div(-a, b, d), Some(-n / d));
+        }
+
+        // Otherwise the quotient is exact or reported as not fitting
+        let n = I256::from(a) * I256::from(b);
+        match math::mul_div(a, b, d) {
+            Some(q) => {
+                assert!(I256::from(q) * I256::from(d) <= n);
+                assert!(I256::from(q + 1) * I256::from(d) > n);
+            }
+            None => assert!(n / I256::from(d) > I256::from(i128::MAX)),
+        }
+
+        let r = math::sqrt_product(a, b);
+        assert!(I256::from(r) * I256::from(r) <= n);
+        assert!(I256::from(r + 1) * I256::from(r + 1) > n);
+
+        assert_eq!(math::cmp_products(a, b, d, 1), n.cmp(&I256::from(d)));
+        assert_eq!(
+            math::cmp_products(-a, b, -d, 1),
+            n.cmp(&I256::from(d)).reverse()
+        );
+    }
+    assert_eq!(math::mul_div(1, 1, 0), None);
+}
+
+#[test]
+fn large_reserves_do_not_overflow() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let admin = Address::generate(&e);
+    let liqpool =
+        create_liqpool_contract_with_fee(&e, &token1.address, &token2.address, &admin, 30);
+    liqpool.set_protocol_fee(&admin, &admin, &2_000);
+
+    // Reserves of about 1.7e31, as 18-decimal tokens with large supplies reach
+    let big = i128::MAX / 10_i128.pow(7);
+    let user1 = Address::generate(&e);
+    token1_admin.mint(&user1, &(10_000 * big));
+    token2_admin.mint(&user1, &(10_000 * big));
+    liqpool.deposit(&user1, &big, &0, &(big / 3), &0);
+
+    let product = || {
+        let (reserve_a, reserve_b) = liqpool.get_reserves();
+        I256::from(reserve_a) * I256::from(reserve_b)
+    };
+    let mut rng = StdRng::seed_from_u64(22);
+    for _ in 0..50 {
+        let k = product();
+        match rng.gen_range(0..4) {
+            0 => {
+                let amount_in = rng.gen_range(1..=big);
+                let out = liqpool.quote_out(&true, &amount_in);
+                assert_eq!(liqpool.swap_exact_in(&user1, &true, &amount_in, &out), out);
+                assert!(product() >= k);
+            }
+            1 => {
+                let out = rng.gen_range(1..=liqpool.get_reserves().0 / 2);
+                let amount_in = liqpool.quote_in(&true, &out);
+                liqpool.swap(&user1, &true, &out, &amount_in);
+                assert!(product() >= k);
+            }
+            2 => {
+                let desired_a = rng.gen_range(1..=big);
+                let desired_b = rng.gen_range(1..=big);
+                let (amount_a, amount_b, shares) = liqpool.quote_deposit(&desired_a, &desired_b);
+                if amount_a > 0 && amount_b > 0 {
+                    let before = liqpool.balance_shares(&user1);
+                    liqpool.deposit(&user1, &desired_a, &0, &desired_b, &0);
+                    assert_eq!(liqpool.balance_shares(&user1), before + shares);
+                }
+            }
+            _ => {
+                let share_amount = rng.gen_range(1..=liqpool.balance_shares(&user1) / 2);
+                let quote = liqpool.quote_withdraw(&share_amount);
+                assert_eq!(liqpool.withdraw(&user1, &share_amount, &0, &0), quote);
+            }
+        }
+    }
+}
+
+#[test]
+fn first_deposit_locks_minimum_liquidity() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+    let user1 = Address::generate(&e);
+    token1_admin.mint(&user1, &100_000);
+    token2_admin.mint(&user1, &100_000);
+
+    // sqrt(1_000 * 1_000) leaves nothing once the locked shares are minted
+    assert_eq!(
+        liqpool.try_deposit(&user1, &1_000, &1_000, &1_000, &1_000),
+        Err(Ok(PoolError::InsufficientLiquidityMinted))
+    );
+    assert_eq!(
+        liqpool.try_quote_deposit(&1_000, &1_000),
+        Err(Ok(PoolError::InsufficientLiquidityMinted))
+    );
+
+    assert_eq!(liqpool.quote_deposit(&4_000, &1_000), (4_000, 1_000, 1_000));
+    liqpool.deposit(&user1, &4_000, &4_000, &1_000, &1_000);
+    assert_eq!(liqpool.balance_shares(&user1), 1_000);
+    assert_eq!(liqpool.balance_shares(&liqpool.address), 1_000);
+    assert_eq!(liqpool.get_total_shares(), 2_000);
+
+    // The locked half of the pool stays behind
+    assert_eq!(liqpool.withdraw(&user1, &1_000, &0, &0), (2_000, 500));
+    assert_eq!(liqpool.get_reserves(), (2_000, 500));
+    assert_eq!(liqpool.get_total_shares(), 1_000);
+}
+
+#[test]
+fn donations_do_not_inflate_the_share_price() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, token2_admin) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+    let attacker = Address::generate(&e);
+    let victim = Address::generate(&e);
+    for user in [&attacker, &victim] {
+        token1_admin.mint(user, &10_000_000);
+        token2_admin.mint(user, &10_000_000);
+    }
+
+    // The attacker takes a single share, then donates to the pool and folds
+    // the donation into the reserves with a tiny swap
+    liqpool.deposit(&attacker, &1_001, &0, &1_001, &0);
+    assert_eq!(liqpool.balance_shares(&attacker), 1);
+    token1.transfer(&attacker, &liqpool.address, &1_000_000);
+    token2.transfer(&attacker, &liqpool.address, &1_000_000);
+
+    // Donations are not counted until the reserves catch up with them
+    assert_eq!(liqpool.quote_deposit(&500, &500), (500, 500, 500));
+    liqpool.swap_exact_in(&attacker, &true, &10, &0);
+    let (reserve_a, reserve_b) = liqpool.get_reserves();
+    assert!(reserve_a > 1_000_000 && reserve_b > 1_000_000);
+
+    // A deposit too small to be worth a share reverts instead of being
+    // absorbed by the existing holders
+    assert_eq!(
+        liqpool.try_deposit(&victim, &500, &0, &500, &0),
+        Err(Ok(PoolError::InsufficientLiquidityMinted))
+    );
+    assert_eq!(token1.balance(&victim), 10_000_000);
+
+    // A real deposit gets its fair share, losing less than one share's worth
+    // to rounding
+    liqpool.deposit(&victim, &500_000, &0, &500_000, &0);
+    let victim_shares = liqpool.balance_shares(&victim);
+    let (out_a, out_b) = liqpool.withdraw(&victim, &victim_shares, &0, &0);
+    let share_value = reserve_a / liqpool.get_total_shares() + 1;
+    assert!(500_000 - out_a <= share_value && out_a <= 500_000);
+    assert!(500_000 - out_b <= share_value && out_b <= 500_000);
+
+    // Most of the donation now belongs to the locked shares
+    let (attacker_a, attacker_b) = liqpool.withdraw(&attacker, &1, &0, &0);
+    assert!(attacker_a < 1_001 + 1_000_000 / 1_000);
+    assert!(attacker_b < 1_001 + 1_000_000 / 1_000);
+}
+
+#[test]
+fn sync_and_skim_reconcile_reserves() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    let (token_a, token_b) = liqpool.get_tokens();
+    let (token1, token2) = (
+        token::Client::new(&e, &token_a),
+        token::Client::new(&e, &token_b),
+    );
+    let treasury = Address::generate(&e);
+    liqpool.set_protocol_fee(&liqpool.get_admin(), &treasury, &5_000);
+    liqpool.swap_exact_in(&user1, &true, &1_000, &0);
+    let reserves = liqpool.get_reserves();
+    assert_eq!(liqpool.get_protocol_fees_accrued(), (1, 0));
+    assert_eq!(liqpool.get_balances(), reserves);
+
+    // Tokens sent directly are held above the reserves until reconciled
+    token1.transfer(&user1, &liqpool.address, &300);
+    token2.transfer(&user1, &liqpool.address, &200);
+    assert_eq!(liqpool.get_balances(), (reserves.0 + 300, reserves.1 + 200));
+
+    let skimmer = Address::generate(&e);
+    assert_eq!(liqpool.skim(&skimmer), (300, 200));
+    assert_eq!(
+        contract_events(&e, &liqpool.address),
+        vec![
+            &e,
+            (
+                liqpool.address.clone(),
+                (symbol_short!("skim"), skimmer.clone()).into_val(&e),
+                (300_i128, 200_i128).into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(token1.balance(&skimmer), 300);
+    assert_eq!(token2.balance(&skimmer), 200);
+    assert_eq!(liqpool.get_balances(), reserves);
+    assert_eq!(liqpool.get_protocol_fees_accrued(), (1, 0));
+    assert_eq!(liqpool.skim(&skimmer), (0, 0));
+
+    token1.transfer(&user1, &liqpool.address, &300);
+    let synced = (reserves.0 + 300, reserves.1);
+    assert_eq!(liqpool.sync(), synced);
+    assert_eq!(
+        contract_events(&e, &liqpool.address),
+        vec![
+            &e,
+            (
+                liqpool.address.clone(),
+                (symbol_short!("sync"),).into_val(&e),
+                synced.into_val(&e)
+            ),
+        ]
+    );
+    assert_eq!(liqpool.get_reserves(), synced);
+    assert_eq!(liqpool.skim(&skimmer), (0, 0));
+
+    // Protocol fees stay out of the reserves
+    assert_eq!(liqpool.collect_protocol_fees(), (1, 0));
+    assert_eq!(liqpool.get_balances(), synced);
+
+    liqpool.pause(&liqpool.get_admin());
+    assert_eq!(liqpool.try_sync(), Err(Ok(PoolError::Paused)));
+}
+
+#[test]
+fn sync_rejects_an_empty_pool() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let (token1, token1_admin) = create_token_contract(&e, &admin1);
+    let (token2, _) = create_token_contract(&e, &admin2);
+    let liqpool = create_liqpool_contract(&e, &token1.address, &token2.address);
+
+    token1_admin.mint(&liqpool.address, &1_000);
+    assert_eq!(liqpool.get_balances(), (1_000, 0));
+    assert_eq!(liqpool.try_sync(), Err(Ok(PoolError::NonPositiveReserves)));
+
+    // Before the first deposit everything the pool holds is excess
+    let user1 = Address::generate(&e);
+    assert_eq!(liqpool.skim(&user1), (1_000, 0));
+    assert_eq!(liqpool.get_balances(), (0, 0));
+}
+
+#[test]
+fn oracle_averages_prices_over_time() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.ledger().set_timestamp(1_000);
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    assert_eq!(liqpool.get_cumulative_prices(), (0, 0, 1_000));
+    assert_eq!(liqpool.try_consult(&0), Err(Ok(PoolError::InvalidWindow)));
+    assert_eq!(
+        liqpool.try_consult(&1),
+        Err(Ok(PoolError::NotEnoughHistory))
+    );
+
+    // One token A is worth one token B for the first hour
+    e.ledger().set_timestamp(4_600);
+    assert_eq!(
+        liqpool.get_cumulative_prices(),
+        (3_600 * PRICE_SCALE, 3_600 * PRICE_SCALE, 4_600)
+    );
+    assert_eq!(liqpool.consult(&3_600), (PRICE_SCALE, PRICE_SCALE));
+
+    // Then the reserves move to (19_637, 5_100) for another hour
+    liqpool.swap(&user1, &false, &4_900, &10_000);
+    let price_a = 5_100 * PRICE_SCALE / 19_637;
+    let price_b = 19_637 * PRICE_SCALE / 5_100;
+    e.ledger().set_timestamp(8_200);
+    assert_eq!(liqpool.consult(&3_600), (price_a, price_b));
+    let average = ((PRICE_SCALE + price_a) / 2, (PRICE_SCALE + price_b) / 2);
+    assert_eq!(liqpool.consult(&7_200), average);
+
+    // A window between observations starts at the older one
+    assert_eq!(liqpool.consult(&3_601), average);
+
+    // Moving the spot price has no effect until time passes
+    liqpool.swap_exact_in(&user1, &false, &50_000, &0);
+    assert_eq!(liqpool.consult(&7_200), average);
+    e.ledger().set_timestamp(8_201);
+    assert!(liqpool.consult(&7_200).0 > average.0);
+}
+
+#[test]
+fn oracle_keeps_a_bounded_history() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.ledger().set_timestamp(1_000);
+
+    let (liqpool, user1) = setup_funded_pool(&e);
+    let observations = || {
+        e.as_contract(&liqpool.address, || {
+            e.storage()
+                .persistent()
+                .get::<_, Vec<Observation>>(&DataKey::Observations)
+                .unwrap()
+        })
+    };
+
+    // Changes within half an hour of the last observation are not stored
+    e.ledger().set_timestamp(2_000);
+    liqpool.swap_exact_in(&user1, &true, &10, &0);
+    assert_eq!(observations().len(), 1);
+
+    for i in 1..=60_u64 {
+        e.ledger().set_timestamp(1_000 + i * 1_800);
+        liqpool.swap_exact_in(&user1, &(i % 2 == 0), &10, &0);
+        assert_eq!(observations().len(), (i as u32 + 1).min(48));
+    }
+
+    // The oldest observations were overwritten
+    let now = e.ledger().timestamp();
+    let oldest = observations().iter().map(|o| o.timestamp).min().unwrap();
+    assert_eq!(oldest, now - 47 * 1_800);
+    assert!(liqpool.try_consult(&(47 * 1_800)).is_ok());
+    assert_eq!(
+        liqpool.try_consult(&(47 * 1_800 + 1)),
+        Err(Ok(PoolError::NotEnoughHistory))
+    );
+}
@@ -0,0 +1,96 @@
+//! Storage TTL management. Instance data is extended by every call that
+//! changes state and share balances and price observations whenever they are
+//! read or written, so LP positions are not archived while their owners are
+//! away.
+
+use soroban_sdk::{contractimpl, contracttype, Address, Env};
+
+use crate::{check_admin, DataKey, PoolError};
+use crate::{LiquidityPool, LiquidityPoolArgs, LiquidityPoolClient};
+
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// When and how far entries are extended, in ledgers. An entry whose TTL
+/// drops below the threshold is extended back to `extend_to`
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct TtlConfig {
+    pub instance_threshold: u32,
+    pub instance_extend_to: u32,
+    /// Applies to the share balances and price observations
+    pub persistent_threshold: u32,
+    pub persistent_extend_to: u32,
+}
+
+fn default_config() -> TtlConfig {
+    TtlConfig {
+        instance_threshold: 6 * DAY_IN_LEDGERS,
+        instance_extend_to: 7 * DAY_IN_LEDGERS,
+        persistent_threshold: 29 * DAY_IN_LEDGERS,
+        persistent_extend_to: 30 * DAY_IN_LEDGERS,
+    }
+}
+
+fn get_config(e: &Env) -> TtlConfig {
+    e.storage()
+        .instance()
+        .get(&DataKey::TtlConfig)
+        .unwrap_or_else(default_config)
+}
+
+pub(crate) fn extend_instance(e: &Env) {
+    let config = get_config(e);
+    e.storage()
+        .instance()
+        .extend_ttl(config.instance_threshold, config.instance_extend_to);
+}
+
+/// Extends a persistent entry the caller knows to exist
+pub(crate) fn extend_persistent(e: &Env, key: &DataKey) {
+    let config = get_config(e);
+    e.storage().persistent().extend_ttl(
+        key,
+        config.persistent_threshold,
+        config.persistent_extend_to,
+    );
+}
+
+#[contractimpl]
+impl LiquidityPool {
+    /// Admin function to change when and how far storage entries are
+    /// extended. Thresholds must not exceed their `extend_to`, which must not
+    /// exceed the network's maximum TTL
+    pub fn set_ttl_config(e: Env, admin: Address, config: TtlConfig) -> Result<(), PoolError> {
+        check_admin(&e, &admin)?;
+
+        let max_ttl = e.storage().max_ttl();
+        if config.instance_threshold > config.instance_extend_to
+            || config.persistent_threshold > config.persistent_extend_to
+            || config.instance_extend_to > max_ttl
+            || config.persistent_extend_to > max_ttl
+        {
+            return Err(PoolError::InvalidTtl);
+        }
+
+        e.storage().instance().set(&DataKey::TtlConfig, &config);
+        extend_instance(&e);
+        Ok(())
+    }
+
+    /// Get the TTL thresholds and extensions in use
+    pub fn get_ttl_config(e: Env) -> TtlConfig {
+        get_config(&e)
+    }
+
+    /// Extend the pool instance, its price observations and `user`'s share
+    /// balance. Anyone can pay for it
+    pub fn bump(e: Env, user: Address) {
+        extend_instance(&e);
+
+        for key in [DataKey::Observations, DataKey::Shares(user)] {
+            if e.storage().persistent().has(&key) {
+                extend_persistent(&e, &key);
+            }
+        }
+    }
+}
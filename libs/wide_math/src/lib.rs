@@ -0,0 +1,79 @@
+//! 256-bit intermediates for the `a * b / d` computations of the contracts.
+//! A product of two 128-bit values is kept as a `(high, low)` pair of u128
+//! halves, so only results that do not fit back in 128 bits fail.
+//!
+//! The helpers are written out by hand because a general 256-bit integer type
+//! adds several kilobytes to each contract, which networks cap in size.
+
+#![no_std]
+
+const LOW_HALF: u128 = u64::MAX as u128;
+
+/// `a * b` in 256 bits, as `(high, low)`
+pub fn wide_mul(a: u128, b: u128) -> (u128, u128) {
+    let (a_high, a_low) = (a >> 64, a & LOW_HALF);
+    let (b_high, b_low) = (b >> 64, b & LOW_HALF);
+
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+
+    let middle = (low_low >> 64) + (high_low & LOW_HALF) + (low_high & LOW_HALF);
+    let low = (low_low & LOW_HALF) | (middle << 64);
+    let high = high_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64);
+    (high, low)
+}
+
+/// `(high, low) / d` and its remainder. `None` if `d` is zero or the
+/// quotient does not fit in u128
+pub fn wide_div((high, low): (u128, u128), d: u128) -> Option<(u128, u128)> {
+    if d == 0 || high >= d {
+        return None;
+    }
+
+    // Long division one bit at a time. `remainder` stays below `d`, the bit
+    // shifted out of it on overflow is carried into the comparison
+    let mut remainder = high;
+    let mut quotient = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1;
+        }
+    }
+    Some((quotient, remainder))
+}
+
+/// `a * b / d` rounded down. `None` if `d` is zero or the result does not fit
+/// in u128
+pub fn mul_div_u128(a: u128, b: u128, d: u128) -> Option<u128> {
+    wide_div(wide_mul(a, b), d).map(|(quotient, _)| quotient)
+}
+
+/// `a * b / d`, truncated towards zero like i128 division. `None` if `d` is
+/// zero or the result does not fit in i128
+pub fn mul_div(a: i128, b: i128, d: i128) -> Option<i128> {
+    let quotient = mul_div_u128(a.unsigned_abs(), b.unsigned_abs(), d.unsigned_abs())?;
+    if (a < 0) ^ (b < 0) ^ (d < 0) {
+        0i128.checked_sub_unsigned(quotient)
+    } else {
+        i128::try_from(quotient).ok()
+    }
+}
+
+/// `a * b / d` rounded up. `None` if an operand is negative, `d` is zero or
+/// the result does not fit in i128
+pub fn mul_div_ceil(a: i128, b: i128, d: i128) -> Option<i128> {
+    let (a, b, d) = (
+        u128::try_from(a).ok()?,
+        u128::try_from(b).ok()?,
+        u128::try_from(d).ok()?,
+    );
+    let (quotient, remainder) = wide_div(wide_mul(a, b), d)?;
+    let quotient = quotient.checked_add((remainder != 0) as u128)?;
+    i128::try_from(quotient).ok()
+}